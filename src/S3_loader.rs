@@ -1,69 +1,1184 @@
-use rusoto_core::Region;
-use rusoto_s3::{S3Client, S3, GetObjectRequest};
+use flate2::read::MultiGzDecoder;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use log::{info, warn};
+use polars::prelude::{CsvReader, DataFrame, ParquetWriter, PolarsResult, SchemaRef, SerReader};
+use rusoto_core::request::HttpClient;
+use rusoto_core::{ByteStream, Region, RusotoError};
+use rusoto_credential::{DefaultCredentialsProvider, StaticProvider};
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload, CompletedPart,
+    CreateMultipartUploadRequest, S3Client, S3, GetObjectError, GetObjectOutput, GetObjectRequest,
+    HeadObjectRequest, ListObjectsV2Request, UploadPartRequest,
+};
 use tokio::io::AsyncReadExt;
 use serde::Deserialize;
-use std::error::Error;
+use std::future::Future;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use crate::error::DataVoltError;
+use crate::metrics::{LoadMetrics, MetricsSink};
+use crate::runtime;
+use crate::schema::{align_schemas, AlignPolicy};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+#[derive(Error, Debug)]
+pub enum S3Error {
+    #[error("Object s3://{bucket}/{key} not found")]
+    NotFound { bucket: String, key: String },
+    #[error("S3 request failed: {0}")]
+    Request(String),
+    #[error("Failed to read object body: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse object: {0}")]
+    Parse(#[from] csv::Error),
+    #[error("Invalid S3 loader configuration: {0}")]
+    Config(String),
+    #[error("Failed to serialize frame: {0}")]
+    Serialize(String),
+}
+
+/// S3 rejects multipart parts smaller than this, except for the last one.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
 
 #[derive(Debug, Deserialize)]
-struct Record {
-    id: i32,
-    value: String,
+pub struct Record {
+    pub id: i32,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    pub etag: Option<String>,
+    pub size: Option<i64>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+impl ObjectMetadata {
+    fn from_output(output: &GetObjectOutput) -> Self {
+        ObjectMetadata {
+            etag: output.e_tag.clone(),
+            size: output.content_length,
+            last_modified: output.last_modified.clone(),
+            content_type: output.content_type.clone(),
+        }
+    }
 }
 
-struct S3Loader {
+pub struct S3Loader {
     bucket_name: String,
     file_key: String,
     s3_client: S3Client,
+    max_retries: u32,
+    retry_backoff: Duration,
+    concurrency: usize,
+    part_size: usize,
+    chunk_bytes: usize,
+    concurrency_limit: Option<Arc<Semaphore>>,
+    dry_run: bool,
+    metrics: Option<Arc<dyn MetricsSink>>,
 }
 
-impl S3Loader {
-    fn new(bucket_name: &str, file_key: &str, aws_access_key_id: &str, aws_secret_access_key: &str) -> Self {
-        let region = Region::default();
-        let s3_client = S3Client::new_with(
-            rusoto_core::request::HttpClient::new().expect("Failed to create HTTP client"),
-            rusoto_credential::StaticProvider::new_minimal(aws_access_key_id.to_string(), aws_secret_access_key.to_string()),
-            region,
-        );
-
-        S3Loader {
-            bucket_name: bucket_name.to_string(),
-            file_key: file_key.to_string(),
+/// Configures an `S3Loader` step by step. Only the bucket is required; everything else
+/// falls back to the default region, no retries and one download at a time. Without
+/// explicit credentials the standard AWS chain is used: the `AWS_ACCESS_KEY_ID` family of
+/// environment variables, then `~/.aws/credentials`, then the instance or container role.
+///
+/// ```no_run
+/// use rusoto_core::Region;
+/// use rust_loaders::s3_loader::S3Loader;
+///
+/// let loader = S3Loader::builder()
+///     .bucket("analytics")
+///     .key("events/2026-10-01.csv")
+///     .region(Region::EuWest1)
+///     .build()?;
+/// # Ok::<(), rust_loaders::error::DataVoltError>(())
+/// ```
+pub struct S3LoaderBuilder {
+    bucket_name: Option<String>,
+    file_key: String,
+    region: Region,
+    region_name: Option<String>,
+    endpoint: Option<String>,
+    credentials: Option<(String, String)>,
+    session_token: Option<String>,
+    client: Option<S3Client>,
+    max_retries: u32,
+    retry_backoff: Duration,
+    concurrency: usize,
+    part_size: usize,
+    chunk_bytes: usize,
+    concurrency_limit: Option<Arc<Semaphore>>,
+    dry_run: bool,
+    metrics: Option<Arc<dyn MetricsSink>>,
+}
+
+impl Default for S3LoaderBuilder {
+    fn default() -> Self {
+        Self {
+            bucket_name: None,
+            file_key: String::new(),
+            region: Region::default(),
+            region_name: None,
+            endpoint: None,
+            credentials: None,
+            session_token: None,
+            client: None,
+            max_retries: 0,
+            retry_backoff: Duration::from_millis(500),
+            concurrency: 1,
+            part_size: 8 * 1024 * 1024,
+            chunk_bytes: 8 * 1024 * 1024,
+            concurrency_limit: None,
+            dry_run: false,
+            metrics: None,
+        }
+    }
+}
+
+impl S3LoaderBuilder {
+    pub fn bucket(mut self, bucket_name: &str) -> Self {
+        self.bucket_name = Some(bucket_name.to_string());
+        self
+    }
+
+    pub fn key(mut self, file_key: &str) -> Self {
+        self.file_key = file_key.to_string();
+        self
+    }
+
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self.region_name = None;
+        self
+    }
+
+    /// Sets the region by its name, e.g. `eu-west-1`. An unknown name fails `build`.
+    pub fn region_name(mut self, name: &str) -> Self {
+        self.region_name = Some(name.to_string());
+        self
+    }
+
+    /// Sends requests to `endpoint` (MinIO, LocalStack, ...) while signing for the region.
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn credentials(mut self, aws_access_key_id: &str, aws_secret_access_key: &str) -> Self {
+        self.credentials = Some((aws_access_key_id.to_string(), aws_secret_access_key.to_string()));
+        self
+    }
+
+    /// The session token that goes with temporary credentials, e.g. from STS. Only used
+    /// together with `credentials`.
+    pub fn session_token(mut self, token: &str) -> Self {
+        self.session_token = Some(token.to_string());
+        self
+    }
+
+    /// Uses a ready-made client, ignoring the region, endpoint and credentials settings.
+    pub fn client(mut self, s3_client: S3Client) -> Self {
+        self.client = Some(s3_client);
+        self
+    }
+
+    /// Extra attempts for requests that fail with a network error or a 5xx, with a linear backoff.
+    pub fn retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// How many objects `load_prefix` downloads, or upload parts are sent, at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Size of each multipart upload part, at least 5 MiB.
+    pub fn part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size;
+        self
+    }
+
+    /// Size of the byte ranges `for_each_batch` downloads one at a time.
+    pub fn chunk_bytes(mut self, chunk_bytes: usize) -> Self {
+        self.chunk_bytes = chunk_bytes;
+        self
+    }
+
+    /// Takes a permit from `limit` for every download and part upload, so loaders sharing
+    /// it never have more requests in flight between them than it has permits.
+    pub fn concurrency_limit(mut self, limit: Arc<Semaphore>) -> Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Makes `upload` and `write_parquet` log what they would send instead of sending it.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Receives duration, row and byte counts after every load, recorded as `s3`.
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    pub fn build(self) -> Result<S3Loader, DataVoltError> {
+        let bucket_name = self.bucket_name
+            .filter(|bucket| !bucket.is_empty())
+            .ok_or_else(|| S3Error::Config("a bucket is required".to_string()))?;
+        if self.concurrency == 0 {
+            return Err(S3Error::Config("concurrency must be at least 1".to_string()).into());
+        }
+        if self.part_size < MIN_PART_SIZE {
+            return Err(S3Error::Config(format!("part size must be at least {} bytes", MIN_PART_SIZE)).into());
+        }
+        if self.chunk_bytes == 0 {
+            return Err(S3Error::Config("chunk bytes must be at least 1".to_string()).into());
+        }
+
+        let s3_client = match self.client {
+            Some(client) => client,
+            None => {
+                let region = match self.region_name {
+                    Some(name) => Region::from_str(&name).map_err(|e| S3Error::Config(format!("{}: {}", e, name)))?,
+                    None => self.region,
+                };
+                let region = match self.endpoint {
+                    Some(endpoint) => Region::Custom { name: region.name().to_string(), endpoint },
+                    None => region,
+                };
+                let http = HttpClient::new().map_err(|e| S3Error::Config(e.to_string()))?;
+                match self.credentials {
+                    Some((key_id, secret)) => {
+                        let provider = StaticProvider::new(key_id, secret, self.session_token, None);
+                        S3Client::new_with(http, provider, region)
+                    },
+                    None => {
+                        let provider = DefaultCredentialsProvider::new().map_err(|e| S3Error::Config(e.to_string()))?;
+                        S3Client::new_with(http, provider, region)
+                    },
+                }
+            },
+        };
+
+        Ok(S3Loader {
+            bucket_name,
+            file_key: self.file_key,
             s3_client,
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff,
+            concurrency: self.concurrency,
+            part_size: self.part_size,
+            chunk_bytes: self.chunk_bytes,
+            concurrency_limit: self.concurrency_limit,
+            dry_run: self.dry_run,
+            metrics: self.metrics,
+        })
+    }
+}
+
+impl S3Loader {
+    pub fn builder() -> S3LoaderBuilder {
+        S3LoaderBuilder::default()
+    }
+
+    pub fn new(bucket_name: &str, file_key: &str, aws_access_key_id: &str, aws_secret_access_key: &str) -> Self {
+        Self::builder()
+            .bucket(bucket_name)
+            .key(file_key)
+            .credentials(aws_access_key_id, aws_secret_access_key)
+            .build()
+            .expect("Failed to create HTTP client")
+    }
+
+    pub fn with_client(bucket_name: &str, file_key: &str, s3_client: S3Client) -> Self {
+        Self::builder()
+            .bucket(bucket_name)
+            .key(file_key)
+            .client(s3_client)
+            .build()
+            .expect("a bucket and a client are all with_client needs")
+    }
+
+    pub async fn load_data(&self) -> Result<Vec<Record>, DataVoltError> {
+        let (records, _) = self.load_with_metadata().await?;
+        Ok(records)
+    }
+
+    /// `load_data` for synchronous callers. See `runtime::block_on` for how this behaves when
+    /// called from inside a tokio runtime.
+    pub fn load_data_blocking(&self) -> Result<Vec<Record>, DataVoltError> {
+        runtime::block_on(self.load_data())?
+    }
+
+    pub async fn load_with_metadata(&self) -> Result<(Vec<Record>, ObjectMetadata), DataVoltError> {
+        let started = Instant::now();
+        let result = self.fetch(&self.file_key).await;
+        let bytes = result.as_ref().ok().and_then(|(_, metadata)| metadata.size).map(|size| size as u64);
+        self.record_metrics(started, bytes, &result, |(records, _)| records.len());
+        Ok(result?)
+    }
+
+    /// Loads the object as a frame with whatever columns it has, parsed by polars the way
+    /// `CSVLoader` parses a local file.
+    pub async fn load_dataframe(&self) -> Result<DataFrame, DataVoltError> {
+        let started = Instant::now();
+        let result = self.fetch_frame(&self.file_key).await;
+        self.record_metrics(started, None, &result, DataFrame::height);
+        let df = result?;
+        info!("Loaded s3://{}/{} with shape: {:?}", self.bucket_name, self.file_key, df.shape());
+        Ok(df)
+    }
+
+    fn record_metrics<T, E: std::fmt::Display>(
+        &self,
+        started: Instant,
+        bytes: Option<u64>,
+        result: &Result<T, E>,
+        rows: impl FnOnce(&T) -> usize,
+    ) {
+        if let Some(sink) = &self.metrics {
+            sink.record_load("s3", &LoadMetrics::from_result(started, bytes, result, rows));
         }
     }
 
-    async fn load_data(&self) -> Result<Vec<Record>, Box<dyn Error>> {
-        let get_req = GetObjectRequest {
+    /// Streams the object in `chunk_bytes` windows fetched with ranged GETs and calls `f` with
+    /// a frame of the complete rows in each, so memory stays around one window however large
+    /// the object is. A row cut by a window boundary is held back and finished from the next
+    /// window. Later batches are parsed with the first batch's column types. The object must
+    /// be uncompressed CSV with a header row and no newlines inside quoted fields.
+    pub async fn for_each_batch<F>(&self, f: F) -> Result<(), DataVoltError>
+    where
+        F: FnMut(&DataFrame) -> Result<(), DataVoltError>,
+    {
+        let started = Instant::now();
+        let result = self.stream_batches(f).await;
+        let bytes = result.as_ref().ok().map(|&(_, size)| size as u64);
+        self.record_metrics(started, bytes, &result, |&(rows, _)| rows);
+        result.map(|_| ())
+    }
+
+    // Returns the rows streamed and the object's size.
+    async fn stream_batches<F>(&self, mut f: F) -> Result<(usize, usize), DataVoltError>
+    where
+        F: FnMut(&DataFrame) -> Result<(), DataVoltError>,
+    {
+        let size = self.object_size(&self.file_key).await?;
+        let mut header: Option<Vec<u8>> = None;
+        let mut schema: Option<SchemaRef> = None;
+        let mut pending = Vec::new();
+        let (mut batches, mut rows) = (0, 0);
+        for window in part_ranges(size, self.chunk_bytes).into_iter().filter(|window| !window.is_empty()) {
+            let last = window.end == size;
+            pending.extend(self.get_range(&self.file_key, window).await?);
+
+            if header.is_none() {
+                match pending.iter().position(|&b| b == b'\n') {
+                    Some(end) => header = Some(pending.drain(..=end).collect()),
+                    None if last => break,
+                    None => continue,
+                }
+            }
+            let complete = if last {
+                pending.len()
+            } else {
+                match pending.iter().rposition(|&b| b == b'\n') {
+                    Some(end) => end + 1,
+                    None => continue,
+                }
+            };
+            let lines: Vec<u8> = pending.drain(..complete).collect();
+            if lines.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            let df = parse_rows(header.as_deref().unwrap_or_default(), lines, schema.clone())?;
+            schema.get_or_insert_with(|| Arc::new(df.schema()));
+            batches += 1;
+            rows += df.height();
+            f(&df)?;
+        }
+
+        info!("Streamed {} rows in {} batches from s3://{}/{}", rows, batches, self.bucket_name, self.file_key);
+        Ok((rows, size))
+    }
+
+    /// Loads every object under `prefix` into one frame, in key order, downloading up to
+    /// `concurrency` at a time. Objects with different columns are aligned to the union of
+    /// their columns, widening types where they disagree.
+    pub async fn load_prefix(&self, prefix: &str) -> Result<DataFrame, DataVoltError> {
+        let started = Instant::now();
+        let result = self.fetch_prefix(prefix).await;
+        self.record_metrics(started, None, &result, DataFrame::height);
+        result
+    }
+
+    async fn fetch_prefix(&self, prefix: &str) -> Result<DataFrame, DataVoltError> {
+        let keys = self.list_keys(prefix).await?;
+        let mut frames: Vec<DataFrame> = stream::iter(&keys)
+            .map(|key| self.fetch_frame(key))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        let Some(first) = frames.first() else {
+            return Ok(DataFrame::default());
+        };
+        if frames.iter().any(|df| df.schema() != first.schema()) {
+            align_schemas(&mut frames, AlignPolicy::Union)?;
+        }
+        let mut frames = frames.into_iter();
+        let mut df = frames.next().unwrap_or_default();
+        for frame in frames {
+            df.vstack_mut(&frame)?;
+        }
+        df.align_chunks();
+
+        info!("Loaded {} objects under s3://{}/{} with shape: {:?}", keys.len(), self.bucket_name, prefix, df.shape());
+        Ok(df)
+    }
+
+    // Loads every object under `prefix` as records. With `skip_missing`, objects deleted
+    // between the listing and the download are logged and skipped instead of failing the
+    // whole load.
+    pub async fn load_prefix_records(&self, prefix: &str, skip_missing: bool) -> Result<Vec<Record>, DataVoltError> {
+        let started = Instant::now();
+        let result = self.fetch_prefix_records(prefix, skip_missing).await;
+        self.record_metrics(started, None, &result, Vec::len);
+        Ok(result?)
+    }
+
+    async fn fetch_prefix_records(&self, prefix: &str, skip_missing: bool) -> Result<Vec<Record>, S3Error> {
+        let keys = self.list_keys(prefix).await?;
+        let mut records = Vec::new();
+        let mut fetched = stream::iter(keys)
+            .map(|key| async move { self.fetch(&key).await })
+            .buffered(self.concurrency);
+        while let Some(result) = fetched.next().await {
+            match result {
+                Ok((object_records, _)) => records.extend(object_records),
+                Err(S3Error::NotFound { bucket, key }) if skip_missing => {
+                    warn!("Skipping s3://{}/{}: object no longer exists", bucket, key);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(records)
+    }
+
+    // Lists the keys under `prefix`, following continuation tokens past the 1000 keys a
+    // single listing returns. Folder placeholders ending in `/` are left out.
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, S3Error> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket_name.clone(),
+                prefix: Some(prefix.to_string()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+            let what = format!("Listing s3://{}/{}", self.bucket_name, prefix);
+            let listing = self.retrying(&what, || self.s3_client.list_objects_v2(request.clone()))
+                .await
+                .map_err(|e| S3Error::Request(e.to_string()))?;
+
+            keys.extend(
+                listing.contents.unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|object| object.key)
+                    .filter(|key| !key.ends_with('/')),
+            );
+
+            match listing.next_continuation_token {
+                Some(token) if listing.is_truncated == Some(true) => continuation_token = Some(token),
+                _ => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn permit(&self) -> Result<Option<SemaphorePermit<'_>>, S3Error> {
+        match &self.concurrency_limit {
+            Some(limit) => Ok(Some(limit.acquire().await.map_err(|e| S3Error::Request(e.to_string()))?)),
+            None => Ok(None),
+        }
+    }
+
+    // Runs `request` until it succeeds, fails with a non-transient error, or runs out of retries.
+    async fn retrying<T, E, Fut>(&self, what: &str, mut request: impl FnMut() -> Fut) -> Result<T, RusotoError<E>>
+    where
+        E: std::error::Error + 'static,
+        Fut: Future<Output = Result<T, RusotoError<E>>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    attempt += 1;
+                    warn!("{} failed ({}), retry {} of {}", what, e, attempt, self.max_retries);
+                    tokio::time::sleep(self.retry_backoff * attempt).await;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch(&self, key: &str) -> Result<(Vec<Record>, ObjectMetadata), S3Error> {
+        let (data, gzip, metadata) = self.download(key).await?;
+        Ok((parse_records(&data, gzip)?, metadata))
+    }
+
+    async fn fetch_frame(&self, key: &str) -> Result<DataFrame, DataVoltError> {
+        let (data, gzip, _) = self.download(key).await?;
+        let data = if gzip {
+            let mut decoded = Vec::new();
+            body_reader(&data, true).read_to_end(&mut decoded)?;
+            decoded
+        } else {
+            data
+        };
+        Ok(CsvReader::new(Cursor::new(data)).finish()?)
+    }
+
+    // Returns the object's body as stored, and whether it is gzip-compressed.
+    async fn download(&self, key: &str) -> Result<(Vec<u8>, bool, ObjectMetadata), S3Error> {
+        let (result, data) = self.get_object(key, None).await?;
+        let metadata = ObjectMetadata::from_output(&result);
+        let gzip = key.ends_with(".gz") || result.content_encoding.as_deref() == Some("gzip");
+        Ok((data, gzip, metadata))
+    }
+
+    async fn get_range(&self, key: &str, range: Range<usize>) -> Result<Vec<u8>, S3Error> {
+        // HTTP ranges are inclusive at both ends.
+        let header = format!("bytes={}-{}", range.start, range.end - 1);
+        let (_, data) = self.get_object(key, Some(header)).await?;
+        Ok(data)
+    }
+
+    async fn object_size(&self, key: &str) -> Result<usize, S3Error> {
+        let request = HeadObjectRequest {
             bucket: self.bucket_name.clone(),
-            key: self.file_key.clone(),
+            key: key.to_string(),
             ..Default::default()
         };
+        let what = format!("Looking up s3://{}/{}", self.bucket_name, key);
+        let head = match self.retrying(&what, || self.s3_client.head_object(request.clone())).await {
+            Ok(head) => head,
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 404 => return Err(self.not_found(key)),
+            Err(e) => return Err(S3Error::Request(e.to_string())),
+        };
+        Ok(head.content_length.unwrap_or_default() as usize)
+    }
 
-        let result = self.s3_client.get_object(get_req).await?;
-        let stream = result.body.ok_or("No body in response")?;
+    fn not_found(&self, key: &str) -> S3Error {
+        S3Error::NotFound {
+            bucket: self.bucket_name.clone(),
+            key: key.to_string(),
+        }
+    }
+
+    // Reads the whole body of the object, or of `range` within it.
+    async fn get_object(&self, key: &str, range: Option<String>) -> Result<(GetObjectOutput, Vec<u8>), S3Error> {
+        let get_req = GetObjectRequest {
+            bucket: self.bucket_name.clone(),
+            key: key.to_string(),
+            range,
+            ..Default::default()
+        };
+        let what = format!("Fetching s3://{}/{}", self.bucket_name, key);
+        let _permit = self.permit().await?;
+        let mut result = match self.retrying(&what, || self.s3_client.get_object(get_req.clone())).await {
+            Ok(result) => result,
+            Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => return Err(self.not_found(key)),
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 404 => return Err(self.not_found(key)),
+            Err(e) => return Err(S3Error::Request(e.to_string())),
+        };
+        let stream = result.body.take().ok_or_else(|| S3Error::Request("No body in response".to_string()))?;
         let mut body = stream.into_async_read();
         let mut data = Vec::new();
         body.read_to_end(&mut data).await?;
+        Ok((result, data))
+    }
 
-        let mut rdr = csv::Reader::from_reader(&data[..]);
-        let mut records = Vec::new();
-        for result in rdr.deserialize() {
-            let record: Record = result?;
-            records.push(record);
+    /// Writes `df` to `key` as Parquet through a multipart upload. Returns the number of parts.
+    pub async fn write_parquet(&self, df: &DataFrame, key: &str) -> Result<usize, DataVoltError> {
+        let mut data = Vec::new();
+        ParquetWriter::new(&mut data)
+            .finish(&mut df.clone())
+            .map_err(|e| S3Error::Serialize(e.to_string()))?;
+        self.upload(key, &data).await
+    }
+
+    /// Uploads `data` in `part_size` parts, up to `concurrency` at a time, each retried on
+    /// transient failures. If a part or the completion fails, the upload is aborted so S3
+    /// doesn't keep the parts already sent. Returns the number of parts, which a dry run
+    /// counts without sending anything.
+    pub async fn upload(&self, key: &str, data: &[u8]) -> Result<usize, DataVoltError> {
+        if self.dry_run {
+            let parts = part_ranges(data.len(), self.part_size).len();
+            info!("Dry run: would upload {} bytes to s3://{}/{} in {} parts", data.len(), self.bucket_name, key, parts);
+            return Ok(parts);
         }
 
-        Ok(records)
+        let create = CreateMultipartUploadRequest {
+            bucket: self.bucket_name.clone(),
+            key: key.to_string(),
+            ..Default::default()
+        };
+        let created = self.retrying("Starting multipart upload", || self.s3_client.create_multipart_upload(create.clone()))
+            .await
+            .map_err(|e| S3Error::Request(e.to_string()))?;
+        let upload_id = created.upload_id.ok_or_else(|| S3Error::Request("No upload id in response".to_string()))?;
+
+        match self.upload_parts(key, &upload_id, data).await {
+            Ok(parts) => {
+                info!("Uploaded {} bytes to s3://{}/{} in {} parts", data.len(), self.bucket_name, key, parts);
+                Ok(parts)
+            },
+            Err(e) => {
+                let abort = AbortMultipartUploadRequest {
+                    bucket: self.bucket_name.clone(),
+                    key: key.to_string(),
+                    upload_id,
+                    ..Default::default()
+                };
+                if let Err(abort_error) = self.s3_client.abort_multipart_upload(abort).await {
+                    warn!("Aborting the upload to s3://{}/{} failed: {}", self.bucket_name, key, abort_error);
+                }
+                Err(e.into())
+            },
+        }
+    }
+
+    async fn upload_parts(&self, key: &str, upload_id: &str, data: &[u8]) -> Result<usize, S3Error> {
+        let mut parts: Vec<CompletedPart> = stream::iter(part_ranges(data.len(), self.part_size).into_iter().enumerate())
+            .map(|(idx, range)| async move {
+                let part_number = idx as i64 + 1;
+                let body = &data[range];
+                let what = format!("Uploading part {} of s3://{}/{}", part_number, self.bucket_name, key);
+                let _permit = self.permit().await?;
+                let output = self.retrying(&what, || {
+                    self.s3_client.upload_part(UploadPartRequest {
+                        bucket: self.bucket_name.clone(),
+                        key: key.to_string(),
+                        upload_id: upload_id.to_string(),
+                        part_number,
+                        content_length: Some(body.len() as i64),
+                        body: Some(ByteStream::from(body.to_vec())),
+                        ..Default::default()
+                    })
+                })
+                .await
+                .map_err(|e| S3Error::Request(e.to_string()))?;
+                Ok::<_, S3Error>(CompletedPart { e_tag: output.e_tag, part_number: Some(part_number) })
+            })
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+        parts.sort_by_key(|part| part.part_number);
+
+        let count = parts.len();
+        let complete = CompleteMultipartUploadRequest {
+            bucket: self.bucket_name.clone(),
+            key: key.to_string(),
+            upload_id: upload_id.to_string(),
+            multipart_upload: Some(CompletedMultipartUpload { parts: Some(parts) }),
+            ..Default::default()
+        };
+        self.retrying("Completing multipart upload", || self.s3_client.complete_multipart_upload(complete.clone()))
+            .await
+            .map_err(|e| S3Error::Request(e.to_string()))?;
+        Ok(count)
+    }
+}
+
+// Every part but the last is exactly `part_size` bytes. Empty input still gets one part,
+// since a multipart upload can't complete without any.
+fn part_ranges(len: usize, part_size: usize) -> Vec<Range<usize>> {
+    (0..len.max(1))
+        .step_by(part_size)
+        .map(|start| start..(start + part_size).min(len))
+        .collect()
+}
+
+// Parses complete CSV lines as if they followed `header`.
+fn parse_rows(header: &[u8], lines: Vec<u8>, schema: Option<SchemaRef>) -> PolarsResult<DataFrame> {
+    let mut data = header.to_vec();
+    data.extend(lines);
+    CsvReader::new(Cursor::new(data)).with_dtypes(schema).finish()
+}
+
+fn is_transient<E>(error: &RusotoError<E>) -> bool {
+    match error {
+        RusotoError::HttpDispatch(_) => true,
+        RusotoError::Unknown(response) => response.status.is_server_error(),
+        _ => false,
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let loader = S3Loader::new("your-bucket-name", "your-file-key", "your-access-key-id", "your-secret-access-key");
-    let data = loader.load_data().await?;
+// Gzip bodies may hold several concatenated members, so they're decoded with a
+// multi-member decoder rather than stopping after the first.
+fn body_reader(data: &[u8], gzip: bool) -> Box<dyn Read + '_> {
+    if gzip {
+        Box::new(MultiGzDecoder::new(data))
+    } else {
+        Box::new(data)
+    }
+}
 
-    for record in data {
-        println!("{:?}", record);
+fn parse_records(data: &[u8], gzip: bool) -> Result<Vec<Record>, S3Error> {
+    let mut rdr = csv::Reader::from_reader(body_reader(data, gzip));
+    let mut records = Vec::new();
+    for result in rdr.deserialize() {
+        let record: Record = result?;
+        records.push(record);
     }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use polars::prelude::{NamedFrom, Series};
+    use rusoto_core::request::{DispatchSignedRequest, DispatchSignedRequestFuture, HttpResponse};
+    use rusoto_core::signature::{SignedRequest, SignedRequestPayload};
+    use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher, MultipleMockRequestDispatcher};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use crate::metrics::CountingSink;
 
-    Ok(())
+    const NO_SUCH_KEY: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>";
+
+    #[tokio::test]
+    async fn test_load_with_metadata() -> Result<(), Box<dyn Error>> {
+        let body = "id,value\n1,a\n2,b\n";
+        let dispatcher = MockRequestDispatcher::default()
+            .with_body(body)
+            .with_header("ETag", "\"9b2cf535f27731c974343645a3985328\"")
+            .with_header("Content-Length", &body.len().to_string())
+            .with_header("Last-Modified", "Wed, 14 Oct 2026 09:30:00 GMT")
+            .with_header("Content-Type", "text/csv");
+        let sink = Arc::new(CountingSink::default());
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .key("data.csv")
+            .metrics(sink.clone())
+            .client(S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1))
+            .build()?;
+
+        let (records, metadata) = loader.load_with_metadata().await?;
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].value, "b");
+        assert_eq!(metadata, ObjectMetadata {
+            etag: Some("\"9b2cf535f27731c974343645a3985328\"".to_string()),
+            size: Some(body.len() as i64),
+            last_modified: Some("Wed, 14 Oct 2026 09:30:00 GMT".to_string()),
+            content_type: Some("text/csv".to_string()),
+        });
+        let recorded = sink.0.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!((recorded[0].0.as_str(), recorded[0].1.rows, recorded[0].1.bytes), ("s3", 2, Some(body.len() as u64)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_data_blocking_matches_async() -> Result<(), Box<dyn Error>> {
+        let dispatcher = MockRequestDispatcher::default().with_body("id,value\n1,a\n2,b\n");
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let loader = S3Loader::with_client("bucket", "data.csv", client);
+        let pairs = |records: Vec<Record>| records.into_iter().map(|r| (r.id, r.value)).collect::<Vec<_>>();
+
+        let blocking = loader.load_data_blocking()?;
+        let awaited = tokio::runtime::Runtime::new()?.block_on(loader.load_data())?;
+
+        assert_eq!(pairs(blocking), vec![(1, "a".to_string()), (2, "b".to_string())]);
+        assert_eq!(pairs(awaited), vec![(1, "a".to_string()), (2, "b".to_string())]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_dataframe_reads_any_schema() -> Result<(), Box<dyn Error>> {
+        let dispatcher = MockRequestDispatcher::default()
+            .with_body("sku,price,in_stock\nA-1,9.99,true\nB-2,4.50,false\nC-3,12.00,true\n");
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let loader = S3Loader::with_client("bucket", "products.csv", client);
+
+        let df = loader.load_dataframe().await?;
+
+        assert_eq!(df.shape(), (3, 3));
+        assert_eq!(df.get_column_names(), &["sku", "price", "in_stock"]);
+        assert_eq!(df.column("price")?.f64()?.get(1), Some(4.5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_requires_bucket() {
+        let result = S3Loader::builder().key("data.csv").credentials("id", "secret").build();
+
+        assert!(matches!(result, Err(DataVoltError::S3(S3Error::Config(_)))));
+    }
+
+    #[test]
+    fn test_builder_rejects_small_parts() {
+        let builder = || S3Loader::builder().bucket("bucket").credentials("id", "secret");
+
+        assert!(matches!(builder().part_size(MIN_PART_SIZE - 1).build(), Err(DataVoltError::S3(S3Error::Config(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_builder_region_name() {
+        let builder = || S3Loader::builder().bucket("bucket").credentials("id", "secret").session_token("token");
+
+        assert!(builder().region_name("eu-west-1").build().is_ok());
+        assert!(matches!(builder().region_name("mars-north-1").build(), Err(DataVoltError::S3(S3Error::Config(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_builder_with_retries() -> Result<(), Box<dyn Error>> {
+        let dispatcher = MultipleMockRequestDispatcher::new(vec![
+            MockRequestDispatcher::with_status(503),
+            MockRequestDispatcher::default().with_body("id,value\n1,a\n"),
+        ]);
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .key("data.csv")
+            .region(Region::EuWest1)
+            .retries(1, Duration::from_millis(1))
+            .concurrency(4)
+            .client(S3Client::new_with(dispatcher, MockCredentialsProvider, Region::EuWest1))
+            .build()?;
+
+        let records = loader.load_data().await?;
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].value, "a");
+
+        let endpoint_loader = S3Loader::builder()
+            .bucket("bucket")
+            .endpoint("http://localhost:9000")
+            .credentials("id", "secret")
+            .build()?;
+        assert_eq!(endpoint_loader.bucket_name, "bucket");
+
+        Ok(())
+    }
+
+    // Answers every request after a short delay, tracking the most requests in flight at once.
+    #[derive(Clone, Default)]
+    struct SlowDispatcher {
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    impl DispatchSignedRequest for SlowDispatcher {
+        fn dispatch(&self, _request: SignedRequest, _timeout: Option<Duration>) -> DispatchSignedRequestFuture {
+            let dispatcher = self.clone();
+            Box::pin(async move {
+                let now = dispatcher.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                dispatcher.peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                dispatcher.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(HttpResponse {
+                    status: Default::default(),
+                    body: ByteStream::from(b"id,value\n1,a\n".to_vec()),
+                    headers: Default::default(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_concurrency_limit_serializes_loads() -> Result<(), Box<dyn Error>> {
+        let loader = |dispatcher: &SlowDispatcher, limit: Option<Arc<Semaphore>>| {
+            let builder = S3Loader::builder()
+                .bucket("bucket")
+                .key("data.csv")
+                .client(S3Client::new_with(dispatcher.clone(), MockCredentialsProvider, Region::UsEast1));
+            match limit {
+                Some(limit) => builder.concurrency_limit(limit).build(),
+                None => builder.build(),
+            }
+        };
+
+        let unlimited = SlowDispatcher::default();
+        let (a, b) = (loader(&unlimited, None)?, loader(&unlimited, None)?);
+        let (first, second) = tokio::join!(a.load_data(), b.load_data());
+        assert_eq!((first?.len(), second?.len()), (1, 1));
+        assert_eq!(unlimited.peak.load(Ordering::SeqCst), 2);
+
+        let limited = SlowDispatcher::default();
+        let limit = Arc::new(Semaphore::new(1));
+        let (a, b) = (loader(&limited, Some(limit.clone()))?, loader(&limited, Some(limit.clone()))?);
+        let (first, second) = tokio::join!(a.load_data(), b.load_data());
+        assert_eq!((first?.len(), second?.len()), (1, 1));
+        assert_eq!(limited.peak.load(Ordering::SeqCst), 1);
+        assert_eq!(limit.available_permits(), 1);
+
+        Ok(())
+    }
+
+    // Serves `data` as one object, answering HEAD with its size and ranged GETs with the
+    // requested bytes, and records every range asked for.
+    #[derive(Clone)]
+    struct RangeDispatcher {
+        data: Arc<Vec<u8>>,
+        ranges: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl DispatchSignedRequest for RangeDispatcher {
+        fn dispatch(&self, request: SignedRequest, _timeout: Option<Duration>) -> DispatchSignedRequestFuture {
+            let mut response = HttpResponse {
+                status: Default::default(),
+                body: ByteStream::from(Vec::new()),
+                headers: Default::default(),
+            };
+            if request.method == "HEAD" {
+                response.headers.insert("content-length", self.data.len().to_string());
+            } else {
+                let range = String::from_utf8(request.headers["range"][0].clone()).unwrap();
+                let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+                let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+                response.status = 206.try_into().unwrap();
+                response.body = ByteStream::from(self.data[start..=end].to_vec());
+                self.ranges.lock().unwrap().push(range);
+            }
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_for_each_batch_reads_ranges() -> Result<(), Box<dyn Error>> {
+        let mut csv = "id,value\n".to_string();
+        for i in 0..40 {
+            csv.push_str(&format!("{},{}\n", i, "x".repeat(i % 7)));
+        }
+        csv.push_str("40,last");
+        let dispatcher = RangeDispatcher { data: Arc::new(csv.clone().into_bytes()), ranges: Default::default() };
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .key("big.csv")
+            .chunk_bytes(64)
+            .client(S3Client::new_with(dispatcher.clone(), MockCredentialsProvider, Region::UsEast1))
+            .build()?;
+
+        let mut ids = Vec::new();
+        let mut values = Vec::new();
+        loader.for_each_batch(|df| {
+            ids.extend(df.column("id")?.i64()?.into_no_null_iter());
+            values.extend(df.column("value")?.str()?.into_iter().map(|v| v.unwrap_or_default().to_string()));
+            Ok(())
+        })
+        .await?;
+
+        assert_eq!(ids, (0..=40).collect::<Vec<i64>>());
+        assert_eq!(values[6], "xxxxxx");
+        assert_eq!(values[40], "last");
+        let ranges = dispatcher.ranges.lock().unwrap();
+        assert_eq!(ranges.len(), csv.len().div_ceil(64));
+        assert_eq!(ranges[1], "bytes=64-127");
+        assert_eq!(ranges.last().unwrap(), &format!("bytes={}-{}", (ranges.len() - 1) * 64, csv.len() - 1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_is_not_found() {
+        let dispatcher = MockRequestDispatcher::with_status(404).with_body(NO_SUCH_KEY);
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let loader = S3Loader::with_client("bucket", "missing.csv", client);
+
+        let err = loader.load_with_metadata().await.unwrap_err();
+
+        assert!(matches!(err, DataVoltError::S3(S3Error::NotFound { ref bucket, ref key }) if bucket == "bucket" && key == "missing.csv"));
+    }
+
+    #[tokio::test]
+    async fn test_load_prefix_skips_missing_objects() -> Result<(), Box<dyn Error>> {
+        let listing = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <ListBucketResult><Name>bucket</Name><Prefix>daily/</Prefix><KeyCount>2</KeyCount>\
+            <IsTruncated>false</IsTruncated>\
+            <Contents><Key>daily/a.csv</Key></Contents>\
+            <Contents><Key>daily/b.csv</Key></Contents>\
+            </ListBucketResult>";
+        let dispatcher = MultipleMockRequestDispatcher::new(vec![
+            MockRequestDispatcher::default().with_body(listing),
+            MockRequestDispatcher::with_status(404).with_body(NO_SUCH_KEY),
+            MockRequestDispatcher::default().with_body("id,value\n2,b\n"),
+        ]);
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let loader = S3Loader::with_client("bucket", "daily/a.csv", client);
+
+        let records = loader.load_prefix_records("daily/", true).await?;
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_prefix_follows_pagination() -> Result<(), Box<dyn Error>> {
+        let page = |keys: &[&str], next: Option<&str>| {
+            let contents: String = keys.iter().map(|key| format!("<Contents><Key>{}</Key></Contents>", key)).collect();
+            let next = next.map(|token| format!("<NextContinuationToken>{}</NextContinuationToken>", token)).unwrap_or_default();
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>bucket</Name>\
+                 <IsTruncated>{}</IsTruncated>{}{}</ListBucketResult>",
+                !next.is_empty(),
+                contents,
+                next
+            )
+        };
+        let dispatcher = MultipleMockRequestDispatcher::new(vec![
+            MockRequestDispatcher::default().with_body(&page(&["parts/", "parts/part-0000.csv"], Some("t1"))),
+            MockRequestDispatcher::default()
+                .with_body(&page(&["parts/part-0001.csv"], None))
+                .with_request_checker(|request: &SignedRequest| {
+                    assert_eq!(request.params.get("continuation-token"), Some(&Some("t1".to_string())));
+                }),
+            MockRequestDispatcher::default().with_body("id,value\n1,a\n2,b\n"),
+            MockRequestDispatcher::default().with_body("id,value,note\n3,c,late\n"),
+        ]);
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let loader = S3Loader::with_client("bucket", "", client);
+
+        let df = loader.load_prefix("parts/").await?;
+
+        assert_eq!(df.shape(), (3, 3));
+        assert_eq!(df.column("note")?.null_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_ranges() {
+        assert_eq!(part_ranges(10, 4), vec![0..4, 4..8, 8..10]);
+        assert_eq!(part_ranges(8, 4), vec![0..4, 4..8]);
+        assert_eq!(part_ranges(0, 4), vec![0..0]);
+    }
+
+    fn recording(dispatcher: MockRequestDispatcher, log: &Arc<Mutex<Vec<String>>>) -> MockRequestDispatcher {
+        let log = Arc::clone(log);
+        dispatcher.with_request_checker(move |request: &SignedRequest| {
+            let part = request.params.get("partNumber").cloned().flatten().unwrap_or_default();
+            // Part bodies are megabytes of padding, so only the other requests' are kept.
+            let body = match &request.payload {
+                Some(SignedRequestPayload::Buffer(bytes)) if part.is_empty() => String::from_utf8_lossy(bytes).to_string(),
+                _ => String::new(),
+            };
+            log.lock().unwrap().push(format!("{} {} {}", request.method, part, body).trim().to_string());
+        })
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_completes_parts_in_order() -> Result<(), Box<dyn Error>> {
+        let created = "<InitiateMultipartUploadResult><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>";
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let dispatcher = MultipleMockRequestDispatcher::new(vec![
+            recording(MockRequestDispatcher::default().with_body(created), &log),
+            recording(MockRequestDispatcher::default().with_header("ETag", "\"etag-1\""), &log),
+            recording(MockRequestDispatcher::with_status(500), &log),
+            recording(MockRequestDispatcher::default().with_header("ETag", "\"etag-2\""), &log),
+            recording(MockRequestDispatcher::default().with_header("ETag", "\"etag-3\""), &log),
+            recording(MockRequestDispatcher::default().with_body("<CompleteMultipartUploadResult/>"), &log),
+        ]);
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .part_size(MIN_PART_SIZE)
+            .retries(1, Duration::from_millis(1))
+            .client(S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1))
+            .build()?;
+
+        let parts = loader.upload("out.bin", &vec![0; 2 * MIN_PART_SIZE + 1]).await?;
+
+        assert_eq!(parts, 3);
+        let log = log.lock().unwrap();
+        assert_eq!(&log[..5], ["POST", "PUT 1", "PUT 2", "PUT 2", "PUT 3"]);
+        let completion = &log[5];
+        assert!(completion.starts_with("POST"));
+        let positions: Vec<usize> = ["etag-1", "etag-2", "etag-3"].iter().map(|tag| completion.find(tag).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_part_aborts_upload() -> Result<(), Box<dyn Error>> {
+        let created = "<InitiateMultipartUploadResult><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>";
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let dispatcher = MultipleMockRequestDispatcher::new(vec![
+            recording(MockRequestDispatcher::default().with_body(created), &log),
+            recording(MockRequestDispatcher::with_status(403), &log),
+            recording(MockRequestDispatcher::with_status(204), &log),
+        ]);
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .part_size(MIN_PART_SIZE)
+            .client(S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1))
+            .build()?;
+
+        let result = loader.upload("out.bin", &vec![0; MIN_PART_SIZE]).await;
+
+        assert!(matches!(result, Err(DataVoltError::S3(S3Error::Request(_)))));
+        assert_eq!(*log.lock().unwrap(), ["POST", "PUT 1", "DELETE"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_upload_sends_nothing() -> Result<(), Box<dyn Error>> {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let dispatcher = recording(MockRequestDispatcher::with_status(500), &log);
+        let loader = S3Loader::builder()
+            .bucket("bucket")
+            .part_size(MIN_PART_SIZE)
+            .dry_run(true)
+            .client(S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1))
+            .build()?;
+
+        let parts = loader.upload("out.bin", &vec![0; MIN_PART_SIZE + 1]).await?;
+        let df = DataFrame::new(vec![Series::new("id", [1, 2, 3])])?;
+        loader.write_parquet(&df, "out.parquet").await?;
+
+        assert_eq!(parts, 2);
+        assert!(log.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_records_reads_every_gzip_member() -> Result<(), Box<dyn Error>> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut data = Vec::new();
+        for part in ["id,value\n1,a\n", "2,b\n3,c\n"] {
+            let mut member = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            member.write_all(part.as_bytes())?;
+            data.extend(member.finish()?);
+        }
+
+        let records = parse_records(&data, true)?;
+
+        assert_eq!(records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        Ok(())
+    }
 }
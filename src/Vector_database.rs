@@ -1,61 +1,1501 @@
-use sqlx::{Pool, Postgres, Row};
-use sqlx::postgres::PgPoolOptions;
-use anyhow::Result;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+use sqlx::{Executor, Pool, Postgres, QueryBuilder, Row};
+use thiserror::Error;
+use crate::connection::{self, PgConnection};
+use crate::credentials::CredentialSource;
+use crate::error::DataVoltError;
+use crate::identifier::{is_identifier, quote_identifier};
+use crate::metrics::{LoadMetrics, MetricsSink};
+use crate::sql_loader::bind_json;
+
+type Result<T, E = DataVoltError> = std::result::Result<T, E>;
+
+const INSERT_BATCH_ROWS: usize = 1000;
+// Postgres rejects statements with more bind parameters than this.
+const MAX_BIND_PARAMS: usize = 65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    Error,
+    Skip,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::Insert => "insert",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InsertCounts {
+    pub inserted: u64,
+    pub skipped: u64,
+    pub updated: u64,
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("Vector {index} has non-finite component {component}: {value}")]
+pub struct NonFiniteVector {
+    pub index: usize,
+    pub component: usize,
+    pub value: f32,
+}
+
+fn check_finite<'a>(vectors: impl IntoIterator<Item = &'a [f32]>) -> Result<(), NonFiniteVector> {
+    for (index, vector) in vectors.into_iter().enumerate() {
+        if let Some((component, &value)) = vector.iter().enumerate().find(|(_, v)| !v.is_finite()) {
+            return Err(NonFiniteVector { index, component, value });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    L2,
+    /// One minus the cosine similarity.
+    Cosine,
+    /// The negated inner product, so the most similar vector still has the smallest distance.
+    InnerProduct,
+}
+
+impl Metric {
+    fn pgvector_operator(self) -> &'static str {
+        match self {
+            Metric::L2 => "<->",
+            Metric::Cosine => "<=>",
+            Metric::InnerProduct => "<#>",
+        }
+    }
+
+    /// The distance between `a` and `b`, matching what pgvector's operator for the metric returns.
+    pub fn distance(self, a: &[f32], b: &[f32]) -> f32 {
+        let dot = |x: &[f32], y: &[f32]| x.iter().zip(y).map(|(x, y)| x * y).sum::<f32>();
+        match self {
+            Metric::L2 => a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt(),
+            Metric::Cosine => 1.0 - dot(a, b) / (dot(a, a).sqrt() * dot(b, b).sqrt()),
+            Metric::InnerProduct => -dot(a, b),
+        }
+    }
+}
+
+// Ordered by distance, then id, so a max-heap of candidates evicts the farthest first.
+struct Candidate {
+    distance: f32,
+    id: i64,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Query components are rounded to multiples of this before keying the search cache, so
+// float noise in an otherwise identical query still hits.
+const CACHE_QUANTUM: f32 = 1e-4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SearchKey {
+    query: Vec<i64>,
+    k: usize,
+    metric: Metric,
+}
+
+impl SearchKey {
+    fn new(query: &[f32], k: usize, metric: Metric) -> Self {
+        SearchKey {
+            query: query.iter().map(|x| (x / CACHE_QUANTUM).round() as i64).collect(),
+            k,
+            metric,
+        }
+    }
+}
+
+struct CachedSearch {
+    hits: Vec<(i64, f32)>,
+    stored_at: Instant,
+    last_used: u64,
+}
+
+// A small LRU of search results. `generation` moves on every invalidation, so a search
+// that started before a write can't store its now-stale results afterwards.
+struct SearchCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<SearchKey, CachedSearch>,
+    generation: u64,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl SearchCache {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        SearchCache {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            generation: 0,
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn get(&mut self, key: &SearchKey) -> Option<Vec<(i64, f32)>> {
+        self.tick += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.stored_at.elapsed() < self.ttl => {
+                entry.last_used = self.tick;
+                self.stats.hits += 1;
+                Some(entry.hits.clone())
+            },
+            _ => {
+                self.stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    fn put(&mut self, key: SearchKey, hits: Vec<(i64, f32)>, generation: u64) {
+        if generation != self.generation || self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let least_recent = self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone());
+            if let Some(least_recent) = least_recent {
+                self.entries.remove(&least_recent);
+            }
+        }
+        self.entries.insert(key, CachedSearch { hits, stored_at: Instant::now(), last_used: self.tick });
+    }
+
+    fn invalidate(&mut self) {
+        self.entries.clear();
+        self.generation += 1;
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("Invalid table name {0:?}")]
+pub struct InvalidTableName(pub String);
+
+/// A table name that is safe to interpolate into SQL, optionally qualified as
+/// `schema.table`. Bare parts must match `[A-Za-z_][A-Za-z0-9_]*` and fold to lower case,
+/// as Postgres folds them; parts in double quotes keep their case and may hold any other
+/// character, with `""` standing for a quote. Always displays fully quoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableName {
+    schema: Option<String>,
+    name: String,
+}
+
+impl TableName {
+    pub fn parse(raw: &str) -> Result<Self, InvalidTableName> {
+        let invalid = || InvalidTableName(raw.to_string());
+        let mut parts = Vec::new();
+        let mut rest = raw;
+        loop {
+            let (part, remainder) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let mut part = String::new();
+                    let mut end = None;
+                    let mut chars = quoted.char_indices().peekable();
+                    while let Some((idx, c)) = chars.next() {
+                        match c {
+                            '"' if chars.peek().is_some_and(|&(_, next)| next == '"') => {
+                                part.push('"');
+                                chars.next();
+                            },
+                            '"' => {
+                                end = Some(idx + 1);
+                                break;
+                            },
+                            c => part.push(c),
+                        }
+                    }
+                    let end = end.ok_or_else(invalid)?;
+                    (part, &quoted[end..])
+                },
+                None => {
+                    let end = rest.find('.').unwrap_or(rest.len());
+                    let part = &rest[..end];
+                    if !is_identifier(part) {
+                        return Err(invalid());
+                    }
+                    (part.to_ascii_lowercase(), &rest[end..])
+                },
+            };
+            if part.is_empty() {
+                return Err(invalid());
+            }
+            parts.push(part);
+
+            match remainder.strip_prefix('.') {
+                Some(next) => rest = next,
+                None if remainder.is_empty() => break,
+                None => return Err(invalid()),
+            }
+        }
+
+        let name = parts.pop().ok_or_else(invalid)?;
+        let schema = parts.pop();
+        if !parts.is_empty() {
+            return Err(invalid());
+        }
+        Ok(TableName { schema, name })
+    }
+
+    // The same table with `suffix` appended to its name, in the same schema.
+    fn with_suffix(&self, suffix: &str) -> Self {
+        TableName {
+            schema: self.schema.clone(),
+            name: format!("{}{}", self.name, suffix),
+        }
+    }
+
+    // Just the quoted name, as `ALTER TABLE ... RENAME TO` expects.
+    fn unqualified(&self) -> String {
+        quote_identifier(&self.name)
+    }
+}
+
+impl std::fmt::Display for TableName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", quote_identifier(schema))?;
+        }
+        f.write_str(&quote_identifier(&self.name))
+    }
+}
+
+// The settings to apply in a transaction before the search query, and the query. Ordering
+// by the metric's operator lets the planner use an index on the column; the exact search
+// turns index scans off so it can't.
+fn search_sql(table: &TableName, metric: Metric, exact: bool) -> (&'static [&'static str], String) {
+    let settings: &[&str] = if exact {
+        &["SET LOCAL enable_indexscan = off", "SET LOCAL enable_bitmapscan = off"]
+    } else {
+        &[]
+    };
+    let sql = format!(
+        "SELECT id, (vector {op} $1::real[]::vector)::real AS distance FROM {table}
+         ORDER BY vector {op} $1::real[]::vector LIMIT $2",
+        op = metric.pgvector_operator(),
+        table = table
+    );
+    (settings, sql)
+}
+
+fn lock(cache: &Mutex<SearchCache>) -> std::sync::MutexGuard<'_, SearchCache> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Applied to every vector before it is stored or used as a query, e.g. a PCA or random projection.
+pub type VectorTransform = Box<dyn Fn(&[f32]) -> Vec<f32> + Send + Sync>;
 
 pub struct VectorDatabase {
     pool: Pool<Postgres>,
-    table_name: String,
+    replica_pool: Option<Pool<Postgres>>,
+    table_name: TableName,
+    validate_finite: bool,
+    transform: Option<Arc<dyn Fn(&[f32]) -> Vec<f32> + Send + Sync>>,
+    reduced_dimension: OnceLock<usize>,
+    operation_log: Option<TableName>,
+    prune_batch_size: Option<usize>,
+    dimension: Option<usize>,
+    search_cache: Option<Arc<Mutex<SearchCache>>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
 }
 
 impl VectorDatabase {
-    pub async fn new(connection_string: &str, table_name: &str) -> Result<Self> {
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(connection_string)
-            .await?;
+    /// Connects to `connection_string`. With a `dimension`, every stored vector must have
+    /// exactly that many components; see `with_dimension`.
+    pub async fn new(connection_string: &str, table_name: &str, dimension: Option<usize>) -> Result<Self> {
+        let db = Self::with_replica(connection_string, None, table_name).await?;
+        Ok(match dimension {
+            Some(dimension) => db.with_dimension(dimension),
+            None => db,
+        })
+    }
+
+    pub async fn from_credentials(credentials: &dyn CredentialSource, table_name: &str) -> Result<Self> {
+        let connection_string = credentials.connection_string().await?;
+        Self::new(&connection_string, table_name, None).await
+    }
+
+    pub async fn with_replica(
+        connection_string: &str,
+        replica_connection_string: Option<&str>,
+        table_name: &str,
+    ) -> Result<Self> {
+        Self::with_connection(&PgConnection::default(), connection_string, replica_connection_string, table_name).await
+    }
+
+    /// Fails with `InvalidTableName` before connecting if `table_name` isn't a valid
+    /// `TableName`.
+    pub async fn with_connection(
+        connection: &PgConnection,
+        connection_string: &str,
+        replica_connection_string: Option<&str>,
+        table_name: &str,
+    ) -> Result<Self> {
+        let table_name = TableName::parse(table_name)?;
+        let pool = connection.connect(connection_string).await?;
+        let replica_pool = match replica_connection_string {
+            Some(replica) => Some(connection.connect(replica).await?),
+            None => None,
+        };
 
         Ok(Self {
             pool,
-            table_name: table_name.to_string(),
+            replica_pool,
+            table_name,
+            validate_finite: true,
+            transform: None,
+            reduced_dimension: OnceLock::new(),
+            operation_log: None,
+            prune_batch_size: None,
+            dimension: None,
+            search_cache: None,
+            metrics: None,
         })
     }
 
+    /// NaN and infinite values poison distance ordering, so inserts reject them unless
+    /// this is turned off.
+    pub fn validate_finite(mut self, enabled: bool) -> Self {
+        self.validate_finite = enabled;
+        self
+    }
+
+    /// Reduces vectors with `transform` before inserting them and before searching with them,
+    /// so stored and query vectors live in the same reduced space.
+    pub fn with_transform(mut self, transform: VectorTransform) -> Self {
+        self.transform = Some(Arc::from(transform));
+        self
+    }
+
+    /// Records every insert and update in `log_table`, in the same transaction as the
+    /// change itself, so the log never disagrees with the table. See `replay`.
+    pub fn with_operation_log(mut self, log_table: &str) -> Result<Self, InvalidTableName> {
+        self.operation_log = Some(TableName::parse(log_table)?);
+        Ok(self)
+    }
+
+    /// Fixes the number of components per stored vector, after any transform. `create_table`
+    /// declares the column as pgvector's `vector(dimension)` when the extension is installed,
+    /// and as a length-checked `REAL[]` otherwise; inserts and searches with any other
+    /// length are rejected before reaching the database.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = Some(dimension);
+        self
+    }
+
+    /// Makes `prune` delete at most `rows` rows per transaction, so a large prune doesn't
+    /// hold its locks for the whole run. A size of zero is treated as one.
+    pub fn prune_batch_size(mut self, rows: usize) -> Self {
+        self.prune_batch_size = Some(rows.max(1));
+        self
+    }
+
+    /// Keeps the results of up to `capacity` recent `search` calls for `ttl`, keyed by the
+    /// query (rounded to `CACHE_QUANTUM`), `k` and the metric. Writes through this handle
+    /// clear the cache; writes from elsewhere don't, so `ttl` bounds how stale a hit can be.
+    pub fn with_search_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.search_cache = Some(Arc::new(Mutex::new(SearchCache::new(capacity, ttl))));
+        self
+    }
+
+    /// Receives duration and row counts after every `query_vectors`, `search` and
+    /// `search_batch` call, recorded as `vector`.
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    fn record_metrics<T>(&self, started: Instant, result: &Result<T>, rows: impl FnOnce(&T) -> usize) {
+        if let Some(sink) = &self.metrics {
+            sink.record_load("vector", &LoadMetrics::from_result(started, None, result, rows));
+        }
+    }
+
+    pub fn search_cache_stats(&self) -> Option<CacheStats> {
+        self.search_cache.as_ref().map(|cache| lock(cache).stats)
+    }
+
+    fn invalidate_search_cache(&self) {
+        if let Some(cache) = &self.search_cache {
+            lock(cache).invalidate();
+        }
+    }
+
+    /// The dimension the transform reduces to, once it has been applied at least once.
+    pub fn reduced_dimension(&self) -> Option<usize> {
+        self.reduced_dimension.get().copied()
+    }
+
+    fn project(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        let Some(transform) = &self.transform else {
+            return Ok(vector.to_vec());
+        };
+
+        let reduced = transform(vector);
+        let expected = *self.reduced_dimension.get_or_init(|| reduced.len());
+        if reduced.len() != expected {
+            return Err(DataVoltError::ProcessingError(format!(
+                "Vector transform returned {} components, expected {}", reduced.len(), expected
+            )));
+        }
+        Ok(reduced)
+    }
+
+    // Projects the vector and checks it against the declared dimension, if any.
+    fn prepare(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        let vector = self.project(vector)?;
+        if let Some(dimension) = self.dimension {
+            if vector.len() != dimension {
+                return Err(DataVoltError::ProcessingError(format!(
+                    "Vector has {} components, but {} stores {}", vector.len(), self.table_name, dimension
+                )));
+            }
+        }
+        Ok(vector)
+    }
+
+    // Writes always go to the primary; reads prefer the replica when one is configured.
+    fn write_pool(&self) -> &Pool<Postgres> {
+        &self.pool
+    }
+
+    fn read_pool(&self) -> &Pool<Postgres> {
+        self.replica_pool.as_ref().unwrap_or(&self.pool)
+    }
+
+    /// Primes the primary pool, and the replica pool if there is one, with `n` open connections.
+    pub async fn warmup(&self, n: usize) -> Result<()> {
+        connection::warmup(self.write_pool(), n).await?;
+        if let Some(replica) = &self.replica_pool {
+            connection::warmup(replica, n).await?;
+        }
+        Ok(())
+    }
+
     pub async fn create_table(&self) -> Result<()> {
+        let column = match self.dimension {
+            Some(dimension) => {
+                let pgvector: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'vector')")
+                    .fetch_one(self.write_pool())
+                    .await?;
+                if pgvector {
+                    format!("vector({}) NOT NULL", dimension)
+                } else {
+                    format!("REAL[] NOT NULL CHECK (cardinality(vector) = {})", dimension)
+                }
+            },
+            None => "REAL[] NOT NULL".to_string(),
+        };
         let query = format!(
             "CREATE TABLE IF NOT EXISTS {} (
                 id SERIAL PRIMARY KEY,
-                vector FLOAT NOT NULL
+                vector {},
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now()
             )",
-            self.table_name
+            self.table_name, column
         );
 
-        sqlx::query(&query).execute(&self.pool).await?;
+        sqlx::query(&query).execute(self.write_pool()).await?;
+
+        if let Some(log_table) = &self.operation_log {
+            let query = format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    seq BIGSERIAL PRIMARY KEY,
+                    op TEXT NOT NULL,
+                    id INTEGER NOT NULL,
+                    vector REAL[] NOT NULL,
+                    logged_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )",
+                log_table
+            );
+            sqlx::query(&query).execute(self.write_pool()).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops and recreates `staging_table`, returning a handle that writes to it through
+    /// the same pools, validation and transform as this one. Operations on the staging
+    /// table aren't logged. Fill it, then swap it in with `promote`.
+    pub async fn rebuild_into(&self, staging_table: &str) -> Result<VectorDatabase> {
+        let staging = VectorDatabase {
+            pool: self.pool.clone(),
+            replica_pool: self.replica_pool.clone(),
+            table_name: TableName::parse(staging_table)?,
+            validate_finite: self.validate_finite,
+            transform: self.transform.clone(),
+            reduced_dimension: self.reduced_dimension.clone(),
+            operation_log: None,
+            prune_batch_size: self.prune_batch_size,
+            dimension: self.dimension,
+            search_cache: None,
+            metrics: self.metrics.clone(),
+        };
+
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", staging.table_name))
+            .execute(self.write_pool())
+            .await?;
+        staging.create_table().await?;
+        Ok(staging)
+    }
+
+    /// Replaces this table with `staging_table` in one transaction. The current table is
+    /// kept as `<table>_previous`, replacing any earlier one, so a bad rebuild can be
+    /// swapped back by hand. Searches running meanwhile wait on the rename locks and see
+    /// either the old table or the new one, never a mix.
+    pub async fn promote(&self, staging_table: &str) -> Result<()> {
+        let staging_table = TableName::parse(staging_table)?;
+        let previous = self.table_name.with_suffix("_previous");
+
+        let mut tx = self.write_pool().begin().await?;
+        tx.execute(format!("DROP TABLE IF EXISTS {}", previous).as_str()).await?;
+        tx.execute(format!("ALTER TABLE IF EXISTS {} RENAME TO {}", self.table_name, previous.unqualified()).as_str()).await?;
+        tx.execute(format!("ALTER TABLE {} RENAME TO {}", staging_table, self.table_name.unqualified()).as_str())
+            .await
+            .map_err(|e| DataVoltError::during(format!("promoting {} to {}", staging_table, self.table_name), e))?;
+        tx.commit().await?;
+        self.invalidate_search_cache();
+        Ok(())
+    }
+
+    async fn log_operations(&self, tx: &mut sqlx::PgConnection, entries: &[(Operation, i32, &[f32])]) -> Result<()> {
+        let Some(log_table) = &self.operation_log else {
+            return Ok(());
+        };
+
+        for chunk in entries.chunks(INSERT_BATCH_ROWS) {
+            let mut builder = QueryBuilder::<Postgres>::new(format!("INSERT INTO {} (op, id, vector) ", log_table));
+            builder.push_values(chunk, |mut row, (op, id, vector)| {
+                row.push_bind(op.as_str()).push_bind(*id).push_bind(*vector);
+            });
+            builder.build().execute(&mut *tx).await?;
+        }
         Ok(())
     }
 
-    pub async fn insert_vector(&self, vector: f32) -> Result<()> {
+    pub async fn insert_vector(&self, vector: &[f32]) -> Result<()> {
+        let vector = self.prepare(vector)?;
+        if self.validate_finite {
+            check_finite([vector.as_slice()])?;
+        }
+
         let query = format!(
-            "INSERT INTO {} (vector) VALUES ($1)",
+            "INSERT INTO {} (vector) VALUES ($1) RETURNING id",
             self.table_name
         );
 
-        sqlx::query(&query)
-            .bind(vector)
-            .execute(&self.pool)
+        let mut tx = self.write_pool().begin().await?;
+        let id: i32 = sqlx::query_scalar(&query)
+            .bind(&vector)
+            .fetch_one(&mut *tx)
             .await?;
+        self.log_operations(&mut tx, &[(Operation::Insert, id, &vector)]).await?;
+        tx.commit().await?;
+        self.invalidate_search_cache();
         Ok(())
     }
 
-    pub async fn query_vectors(&self) -> Result<Vec<f32>> {
+    /// Inserts `vectors` in one transaction and returns their generated ids in input order.
+    /// Each vector is a single bind parameter, so rows go in as multi-row INSERTs of up to
+    /// `MAX_BIND_PARAMS` rows.
+    pub async fn insert_batch(&self, vectors: &[Vec<f32>]) -> Result<Vec<i64>> {
+        let vectors = vectors.iter().map(|vector| self.prepare(vector)).collect::<Result<Vec<_>>>()?;
+        if self.validate_finite {
+            check_finite(vectors.iter().map(Vec::as_slice))?;
+        }
+
+        let mut ids: Vec<i32> = Vec::with_capacity(vectors.len());
+        let mut tx = self.write_pool().begin().await?;
+        for chunk in vectors.chunks(MAX_BIND_PARAMS) {
+            let mut builder = QueryBuilder::<Postgres>::new(format!("INSERT INTO {} (vector) ", self.table_name));
+            builder.push_values(chunk, |mut row, vector| {
+                row.push_bind(vector.as_slice());
+            });
+            builder.push(" RETURNING id");
+
+            // The sequence hands out ids in VALUES order, and RETURNING makes no promise
+            // about row order, so sorting lines the ids back up with the input.
+            let mut chunk_ids: Vec<i32> = builder.build_query_scalar().fetch_all(&mut *tx).await
+                .map_err(|e| DataVoltError::during(format!("batch insert into {}", self.table_name), e))?;
+            chunk_ids.sort_unstable();
+            ids.extend(chunk_ids);
+        }
+
+        let logged: Vec<_> = ids.iter().zip(&vectors).map(|(id, vector)| (Operation::Insert, *id, vector.as_slice())).collect();
+        self.log_operations(&mut tx, &logged).await?;
+        tx.commit().await?;
+        self.invalidate_search_cache();
+        Ok(ids.into_iter().map(i64::from).collect())
+    }
+
+    pub async fn insert_batch_with_ids(&self, rows: &[(i32, Vec<f32>)], on_conflict: OnConflict) -> Result<InsertCounts> {
+        let rows = rows.iter()
+            .map(|(id, vector)| Ok((*id, self.prepare(vector)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let rows = rows.as_slice();
+        if self.validate_finite {
+            check_finite(rows.iter().map(|(_, vector)| vector.as_slice()))?;
+        }
+
+        let vectors: HashMap<i32, &[f32]> = rows.iter().map(|(id, vector)| (*id, vector.as_slice())).collect();
+        let mut counts = InsertCounts::default();
+        let mut logged = Vec::new();
+        let mut tx = self.write_pool().begin().await?;
+
+        for chunk in rows.chunks(INSERT_BATCH_ROWS) {
+            let mut builder = QueryBuilder::<Postgres>::new(format!("INSERT INTO {} (id, vector) ", self.table_name));
+            builder.push_values(chunk, |mut row, (id, vector)| {
+                row.push_bind(*id).push_bind(vector.as_slice());
+            });
+
+            match on_conflict {
+                OnConflict::Error => {
+                    let result = builder.build().execute(&mut *tx).await
+                        .map_err(|e| DataVoltError::during(format!("batch insert into {}", self.table_name), e))?;
+                    counts.inserted += result.rows_affected();
+                    logged.extend(chunk.iter().map(|(id, vector)| (Operation::Insert, *id, vector.as_slice())));
+                },
+                OnConflict::Skip => {
+                    builder.push(" ON CONFLICT (id) DO NOTHING RETURNING id");
+                    let inserted = builder.build().fetch_all(&mut *tx).await?;
+                    counts.inserted += inserted.len() as u64;
+                    counts.skipped += (chunk.len() - inserted.len()) as u64;
+                    for row in inserted {
+                        let id: i32 = row.get("id");
+                        logged.push((Operation::Insert, id, vectors[&id]));
+                    }
+                },
+                OnConflict::Update => {
+                    // xmax is zero only for freshly inserted tuples, which separates inserts from updates.
+                    builder.push(" ON CONFLICT (id) DO UPDATE SET vector = EXCLUDED.vector RETURNING id, (xmax = 0) AS inserted");
+                    for row in builder.build().fetch_all(&mut *tx).await? {
+                        let id: i32 = row.get("id");
+                        let op = if row.get::<bool, _>("inserted") {
+                            counts.inserted += 1;
+                            Operation::Insert
+                        } else {
+                            counts.updated += 1;
+                            Operation::Update
+                        };
+                        logged.push((op, id, vectors[&id]));
+                    }
+                },
+            }
+        }
+
+        self.log_operations(&mut tx, &logged).await?;
+        tx.commit().await?;
+        self.invalidate_search_cache();
+        Ok(counts)
+    }
+
+    /// Rebuilds this table from an operation log written by `with_operation_log`, keeping
+    /// the last logged vector for each id and removing ids whose last operation was a
+    /// delete. Returns the number of rows written or removed.
+    pub async fn replay(&self, log_table: &str) -> Result<u64> {
+        let log_table = TableName::parse(log_table)?;
+        let latest = format!("SELECT DISTINCT ON (id) id, op, vector FROM {} ORDER BY id, seq DESC", log_table);
+        let mut tx = self.write_pool().begin().await?;
+        let query = format!(
+            "INSERT INTO {table} (id, vector)
+             SELECT id, vector FROM ({latest}) AS latest WHERE op <> $1
+             ON CONFLICT (id) DO UPDATE SET vector = EXCLUDED.vector",
+            table = self.table_name,
+            latest = latest
+        );
+        let written = sqlx::query(&query).bind(Operation::Delete.as_str()).execute(&mut *tx).await?;
+        let query = format!(
+            "DELETE FROM {table} WHERE id IN (SELECT id FROM ({latest}) AS latest WHERE op = $1)",
+            table = self.table_name,
+            latest = latest
+        );
+        let removed = sqlx::query(&query).bind(Operation::Delete.as_str()).execute(&mut *tx).await?;
+
+        // Explicit ids don't advance the SERIAL sequence, so move it past the replayed rows.
+        let query = format!(
+            "SELECT setval(pg_get_serial_sequence($1, 'id'), max(id)) FROM {} HAVING count(*) > 0",
+            self.table_name
+        );
+        sqlx::query(&query).bind(self.table_name.to_string()).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        self.invalidate_search_cache();
+        Ok(written.rows_affected() + removed.rows_affected())
+    }
+
+    /// Deletes the rows matching `predicate_sql`, a condition over this table's columns
+    /// with `$1`, `$2`, ... bound from `params`, and returns how many were removed. Each
+    /// batch (see `prune_batch_size`) is deleted in its own transaction, along with its
+    /// entries in the operation log, so `replay` removes pruned rows too.
+    pub async fn prune(&self, predicate_sql: &str, params: &[Value]) -> Result<u64> {
+        let mut query = match self.prune_batch_size {
+            Some(_) => format!(
+                "DELETE FROM {table} WHERE id IN (SELECT id FROM {table} WHERE ({predicate}) LIMIT ${limit})",
+                table = self.table_name,
+                predicate = predicate_sql,
+                limit = params.len() + 1
+            ),
+            None => format!("DELETE FROM {} WHERE ({})", self.table_name, predicate_sql),
+        };
+        if self.operation_log.is_some() {
+            query.push_str(" RETURNING id, vector::real[] AS vector");
+        }
+
+        let mut pruned = 0;
+        loop {
+            let mut statement = sqlx::query(&query);
+            for value in params {
+                statement = bind_json(statement, value).map_err(DataVoltError::InvalidConfig)?;
+            }
+            if let Some(batch) = self.prune_batch_size {
+                statement = statement.bind(batch as i64);
+            }
+
+            let mut tx = self.write_pool().begin().await?;
+            let deleted = if self.operation_log.is_some() {
+                let rows: Vec<(i32, Vec<f32>)> = statement.fetch_all(&mut *tx).await
+                    .map_err(|e| DataVoltError::during(format!("pruning {}", self.table_name), e))?
+                    .iter()
+                    .map(|row| (row.get("id"), row.get("vector")))
+                    .collect();
+                let logged: Vec<_> = rows.iter().map(|(id, vector)| (Operation::Delete, *id, vector.as_slice())).collect();
+                self.log_operations(&mut tx, &logged).await?;
+                rows.len() as u64
+            } else {
+                statement.execute(&mut *tx).await
+                    .map_err(|e| DataVoltError::during(format!("pruning {}", self.table_name), e))?
+                    .rows_affected()
+            };
+            tx.commit().await?;
+            self.invalidate_search_cache();
+
+            pruned += deleted;
+            match self.prune_batch_size {
+                Some(batch) if deleted >= batch as u64 => continue,
+                _ => return Ok(pruned),
+            }
+        }
+    }
+
+    /// Prunes rows whose `created_at` is before `cutoff`. Tables created before that
+    /// column was added to `create_table` need it added by hand first.
+    pub async fn prune_older_than(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        self.prune("created_at < $1::timestamptz", &[Value::String(cutoff.to_rfc3339())]).await
+    }
+
+    pub async fn query_vectors(&self) -> Result<Vec<Vec<f32>>> {
+        let started = Instant::now();
+        let result = self.fetch_vectors().await;
+        self.record_metrics(started, &result, Vec::len);
+        result
+    }
+
+    async fn fetch_vectors(&self) -> Result<Vec<Vec<f32>>> {
         let query = format!(
-            "SELECT vector FROM {}",
+            "SELECT vector::real[] AS vector FROM {} ORDER BY id",
             self.table_name
         );
 
         let rows = sqlx::query(&query)
-            .fetch_all(&self.pool)
+            .fetch_all(self.read_pool())
             .await?;
 
         Ok(rows.iter().map(|row| row.get("vector")).collect())
     }
+
+    /// Returns the `k` rows nearest to `query` under `metric`, nearest first. A pgvector
+    /// column is searched with the metric's operator so an index on it can be used;
+    /// otherwise rows are streamed from the table and ranked here, holding only the
+    /// current top `k` in memory.
+    pub async fn search(&self, query: &[f32], k: usize, metric: Metric) -> Result<Vec<(i64, f32)>> {
+        let started = Instant::now();
+        let result = self.search_cached(query, k, metric).await;
+        self.record_metrics(started, &result, Vec::len);
+        result
+    }
+
+    async fn search_cached(&self, query: &[f32], k: usize, metric: Metric) -> Result<Vec<(i64, f32)>> {
+        let query = self.prepare(query)?;
+        let Some(cache) = &self.search_cache else {
+            return self.search_uncached(&query, k, metric).await;
+        };
+
+        let key = SearchKey::new(&query, k, metric);
+        let generation = {
+            let mut cache = lock(cache);
+            if let Some(hits) = cache.get(&key) {
+                return Ok(hits);
+            }
+            cache.generation
+        };
+        let hits = self.search_uncached(&query, k, metric).await?;
+        lock(cache).put(key, hits.clone(), generation);
+        Ok(hits)
+    }
+
+    async fn search_uncached(&self, query: &[f32], k: usize, metric: Metric) -> Result<Vec<(i64, f32)>> {
+        if self.uses_pgvector().await? {
+            return self.search_operator(query, k, metric, false).await;
+        }
+
+        let sql = format!("SELECT id, vector::real[] AS vector FROM {}", self.table_name);
+        let mut rows = sqlx::query(&sql).fetch(self.read_pool());
+        let mut nearest = BinaryHeap::with_capacity(k + 1);
+        while let Some(row) = rows.try_next().await? {
+            let id: i32 = row.get("id");
+            let vector: Vec<f32> = row.get("vector");
+            if vector.len() != query.len() {
+                return Err(DataVoltError::ProcessingError(format!(
+                    "Row {} has {} components, but the query has {}", id, vector.len(), query.len()
+                )));
+            }
+            nearest.push(Candidate { distance: metric.distance(query, &vector), id: id.into() });
+            if nearest.len() > k {
+                nearest.pop();
+            }
+        }
+        Ok(nearest.into_sorted_vec().into_iter().map(|c| (c.id, c.distance)).collect())
+    }
+
+    async fn search_operator(&self, query: &[f32], k: usize, metric: Metric, exact: bool) -> Result<Vec<(i64, f32)>> {
+        let (settings, sql) = search_sql(&self.table_name, metric, exact);
+        let statement = sqlx::query(&sql).bind(query).bind(k as i64);
+        let rows = if settings.is_empty() {
+            statement.fetch_all(self.read_pool()).await?
+        } else {
+            let mut tx = self.read_pool().begin().await?;
+            for setting in settings {
+                tx.execute(*setting).await?;
+            }
+            let rows = statement.fetch_all(&mut *tx).await?;
+            tx.commit().await?;
+            rows
+        };
+        Ok(rows.iter().map(|row| (row.get::<i32, _>("id").into(), row.get("distance"))).collect())
+    }
+
+    // The operator search with index scans turned off, so the planner has to answer with
+    // an exact sequential scan. Without pgvector there's no index, and `search` is exact.
+    async fn search_exact(&self, query: &[f32], k: usize, metric: Metric) -> Result<Vec<(i64, f32)>> {
+        if !self.uses_pgvector().await? {
+            return self.search_uncached(query, k, metric).await;
+        }
+        self.search_operator(query, k, metric, true).await
+    }
+
+    // True when the vector column has pgvector's type rather than REAL[]; see `create_table`.
+    async fn uses_pgvector(&self) -> Result<bool> {
+        let is_vector: Option<bool> = sqlx::query_scalar(
+            "SELECT t.typname = 'vector' FROM pg_attribute a JOIN pg_type t ON t.oid = a.atttypid
+             WHERE a.attrelid = to_regclass($1) AND a.attname = 'vector'",
+        )
+        .bind(self.table_name.to_string())
+        .fetch_optional(self.read_pool())
+        .await?;
+        Ok(is_vector.unwrap_or(false))
+    }
+
+    /// Runs `search` once per query, at most `concurrency` at a time. The concurrency is
+    /// capped at the pool's `max_connections` so a large batch queues on the pool instead
+    /// of timing out waiting for connections. Results are returned in the same order as
+    /// `queries`.
+    pub async fn search_batch(&self, queries: &[Vec<f32>], k: usize, metric: Metric, concurrency: usize) -> Result<Vec<Vec<(i64, f32)>>> {
+        let started = Instant::now();
+        let result = self.search_all(queries, k, metric, concurrency).await;
+        self.record_metrics(started, &result, |hits| hits.iter().map(Vec::len).sum());
+        result
+    }
+
+    // Goes through the cache like `search`, but the batch is recorded as one load.
+    async fn search_all(&self, queries: &[Vec<f32>], k: usize, metric: Metric, concurrency: usize) -> Result<Vec<Vec<(i64, f32)>>> {
+        let max_connections = self.read_pool().options().get_max_connections() as usize;
+        let limit = concurrency.clamp(1, max_connections);
+
+        let mut results: Vec<(usize, Vec<(i64, f32)>)> = stream::iter(queries.iter().enumerate())
+            .map(|(idx, query)| async move { self.search_cached(query, k, metric).await.map(|hits| (idx, hits)) })
+            .buffer_unordered(limit)
+            .try_collect()
+            .await?;
+
+        results.sort_by_key(|(idx, _)| *idx);
+        Ok(results.into_iter().map(|(_, hits)| hits).collect())
+    }
+
+    /// Measures recall@k of `search` under `metric`, which can use an approximate index,
+    /// against an exact scan, using up to `sample_size` vectors drawn at random from the
+    /// table as queries. Returns the mean fraction of exact neighbours `search` also found.
+    pub async fn evaluate_recall(&self, sample_size: usize, k: usize, metric: Metric) -> Result<f64> {
+        let sample_sql = format!("SELECT vector::real[] AS vector FROM {} ORDER BY random() LIMIT $1", self.table_name);
+        let samples: Vec<Vec<f32>> = sqlx::query(&sample_sql)
+            .bind(sample_size as i64)
+            .fetch_all(self.read_pool())
+            .await?
+            .iter()
+            .map(|row| row.get("vector"))
+            .collect();
+
+        let mut total = 0.0;
+        for query in &samples {
+            let exact: HashSet<i64> = self.search_exact(query, k, metric).await?.into_iter().map(|(id, _)| id).collect();
+            let approximate: HashSet<i64> = self.search_uncached(query, k, metric).await?.into_iter().map(|(id, _)| id).collect();
+            total += if exact.is_empty() {
+                1.0
+            } else {
+                exact.intersection(&approximate).count() as f64 / exact.len() as f64
+            };
+        }
+
+        if samples.is_empty() {
+            return Ok(1.0);
+        }
+        Ok(total / samples.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+    fn lazy_pool(url: &str) -> Pool<Postgres> {
+        PgConnection::default().connect_lazy(url).expect("valid connection string")
+    }
+
+    fn lazy_db(pool: Pool<Postgres>, replica_pool: Option<Pool<Postgres>>) -> VectorDatabase {
+        VectorDatabase {
+            pool,
+            replica_pool,
+            table_name: TableName::parse("embeddings").unwrap(),
+            validate_finite: true,
+            transform: None,
+            reduced_dimension: OnceLock::new(),
+            operation_log: None,
+            prune_batch_size: None,
+            dimension: None,
+            search_cache: None,
+            metrics: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_use_replica_and_writes_use_primary() {
+        let db = lazy_db(
+            lazy_pool("postgres://user@primary:5432/vectors"),
+            Some(lazy_pool("postgres://user@replica:5432/vectors")),
+        );
+
+        assert_eq!(db.write_pool().connect_options().get_host(), "primary");
+        assert_eq!(db.read_pool().connect_options().get_host(), "replica");
+    }
+
+    #[tokio::test]
+    async fn test_pools_use_shared_connection_settings() {
+        let connection = PgConnection {
+            max_connections: 12,
+            acquire_timeout: std::time::Duration::from_secs(3),
+            ..Default::default()
+        };
+        let db = lazy_db(
+            connection.connect_lazy("postgres://user@primary:5432/vectors").unwrap(),
+            Some(connection.connect_lazy("postgres://user@replica:5432/vectors").unwrap()),
+        );
+
+        for pool in [db.write_pool(), db.read_pool()] {
+            assert_eq!(pool.options().get_max_connections(), 12);
+            assert_eq!(pool.options().get_acquire_timeout(), std::time::Duration::from_secs(3));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_fall_back_to_primary() {
+        let db = lazy_db(lazy_pool("postgres://user@primary:5432/vectors"), None);
+
+        assert_eq!(db.read_pool().connect_options().get_host(), "primary");
+    }
+
+    #[test]
+    fn test_table_names_are_validated_and_quoted() {
+        let parsed = |raw: &str| TableName::parse(raw).map(|name| name.to_string());
+
+        assert_eq!(parsed("Embeddings").unwrap(), r#""embeddings""#);
+        assert_eq!(parsed(r#"search."MixedCase""#).unwrap(), r#""search"."MixedCase""#);
+        assert_eq!(parsed(r#""odd "" name.v2""#).unwrap(), r#""odd "" name.v2""#);
+        for malicious in ["x; DROP TABLE users", "x--", "1abc", "a.b.c", "", r#""unterminated"#, r#""a"b"#, "a."] {
+            assert_eq!(TableName::parse(malicious), Err(InvalidTableName(malicious.to_string())));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malicious_table_name_is_rejected_before_connecting() {
+        let err = VectorDatabase::new("postgres://user@unreachable:1/vectors", "x; DROP TABLE users", None)
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, DataVoltError::InvalidTableName(_)));
+    }
+
+    #[tokio::test]
+    async fn test_insert_batch_rejects_nan() {
+        let db = lazy_db(lazy_pool("postgres://user@primary:5432/vectors"), None);
+
+        let rows = [(1, vec![0.5, 1.0]), (2, vec![1.0, f32::NAN]), (3, vec![1.5, 2.0])];
+        let err = db.insert_batch_with_ids(&rows, OnConflict::Error)
+            .await
+            .unwrap_err();
+        let DataVoltError::NonFiniteVector(err) = err else {
+            panic!("expected a NonFiniteVector error, got {:?}", err);
+        };
+
+        assert_eq!((err.index, err.component), (1, 1));
+        assert!(err.value.is_nan());
+    }
+
+    #[tokio::test]
+    async fn test_transform_halves_dimension() {
+        let db = lazy_db(lazy_pool("postgres://user@primary:5432/vectors"), None)
+            .with_transform(Box::new(|v: &[f32]| v.chunks(2).map(|pair| pair.iter().sum::<f32>() / pair.len() as f32).collect()));
+
+        assert_eq!(db.project(&[1.0, 3.0, 5.0, 7.0]).unwrap(), vec![2.0, 6.0]);
+        assert_eq!(db.reduced_dimension(), Some(2));
+        assert!(db.project(&[1.0, 3.0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dimension_is_checked_before_querying() {
+        let db = lazy_db(lazy_pool("postgres://user@primary:5432/vectors"), None).with_dimension(3);
+
+        assert!(db.insert_vector(&[1.0, 2.0]).await.unwrap_err().to_string().contains("has 2 components"));
+        assert!(db.search_batch(&[vec![0.0; 4]], 1, Metric::L2, 1).await.is_err());
+    }
+
+    #[test]
+    fn test_metric_distances() {
+        let (a, b) = ([1.0, 0.0], [3.0, 4.0]);
+
+        assert_eq!(Metric::L2.distance(&a, &b), 20f32.sqrt());
+        assert!((Metric::Cosine.distance(&a, &b) - 0.4).abs() < 1e-6);
+        assert_eq!(Metric::InnerProduct.distance(&a, &b), -3.0);
+    }
+
+    #[test]
+    fn test_exact_search_turns_off_index_scans() {
+        let table = TableName::parse("embeddings").unwrap();
+        let (approximate_settings, approximate) = search_sql(&table, Metric::L2, false);
+        let (exact_settings, exact) = search_sql(&table, Metric::L2, true);
+
+        assert!(approximate_settings.is_empty());
+        assert!(approximate.contains("ORDER BY vector <-> $1::real[]::vector LIMIT $2"));
+        assert_eq!(exact_settings, ["SET LOCAL enable_indexscan = off", "SET LOCAL enable_bitmapscan = off"]);
+        assert_eq!(exact, approximate);
+    }
+
+    #[test]
+    fn test_search_cache_evicts_least_recent_and_expires() {
+        let mut cache = SearchCache::new(2, Duration::from_secs(60));
+        let key = |x: f32| SearchKey::new(&[x, 1.0], 5, Metric::L2);
+        cache.put(key(1.0), vec![(1, 0.0)], 0);
+        cache.put(key(2.0), vec![(2, 0.0)], 0);
+        assert!(cache.get(&key(1.00001)).is_some());
+        cache.put(key(3.0), vec![(3, 0.0)], 0);
+
+        assert_eq!(cache.get(&key(1.0)), Some(vec![(1, 0.0)]));
+        assert_eq!(cache.get(&key(2.0)), None);
+        assert!(cache.get(&SearchKey::new(&[1.0, 1.0], 5, Metric::Cosine)).is_none());
+
+        // Results computed before an invalidation are dropped rather than stored.
+        cache.invalidate();
+        cache.put(key(4.0), vec![(4, 0.0)], 0);
+        assert_eq!(cache.get(&key(4.0)), None);
+
+        let mut expiring = SearchCache::new(2, Duration::ZERO);
+        expiring.put(key(1.0), vec![(1, 0.0)], 0);
+        assert_eq!(expiring.get(&key(1.0)), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_search_cache_hits_until_insert() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "search_cache_test", Some(2)).await?
+            .with_search_cache(16, Duration::from_secs(60));
+        db.create_table().await?;
+        db.insert_batch(&[vec![1.0, 0.0], vec![0.0, 1.0]]).await?;
+
+        let first = db.search(&[0.9, 0.1], 1, Metric::L2).await?;
+        let repeated = db.search(&[0.9, 0.1], 1, Metric::L2).await?;
+        let after_repeat = db.search_cache_stats();
+        db.insert_vector(&[0.9, 0.1]).await?;
+        let after_insert = db.search(&[0.9, 0.1], 1, Metric::L2).await?;
+
+        sqlx::query("DROP TABLE search_cache_test").execute(db.write_pool()).await?;
+        assert_eq!(first, repeated);
+        assert_eq!(after_repeat, Some(CacheStats { hits: 1, misses: 1 }));
+        assert_eq!(after_insert, vec![(3, 0.0)]);
+        assert_eq!(db.search_cache_stats(), Some(CacheStats { hits: 1, misses: 2 }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_search_ranks_by_metric() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "knn_test", Some(2)).await?;
+        db.create_table().await?;
+        let rows = [(1, vec![1.0, 0.0]), (2, vec![0.0, 1.0]), (3, vec![3.0, 3.0]), (4, vec![-1.0, 0.0])];
+        db.insert_batch_with_ids(&rows, OnConflict::Error).await?;
+
+        let query = [0.9, 0.1];
+        let l2 = db.search(&query, 2, Metric::L2).await?;
+        let cosine = db.search(&query, 1, Metric::Cosine).await?;
+        let inner = db.search(&query, 1, Metric::InnerProduct).await?;
+
+        sqlx::query("DROP TABLE knn_test").execute(db.write_pool()).await?;
+        assert_eq!(l2.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!((l2[0].1 - 0.02f32.sqrt()).abs() < 1e-5);
+        assert_eq!(cosine[0].0, 1);
+        assert_eq!(inner[0].0, 3);
+        assert!((inner[0].1 + 3.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_transform_applies_to_inserts_and_queries() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "transform_test", None).await?
+            .with_transform(Box::new(|v: &[f32]| v.chunks(2).map(|pair| pair.iter().sum::<f32>() / pair.len() as f32).collect()));
+        db.create_table().await?;
+        db.insert_vector(&[8.0, 6.0, 4.0, 2.0]).await?;
+        db.insert_vector(&[2.0, 0.0, 0.0, 0.0]).await?;
+
+        let stored = db.query_vectors().await?;
+        let hits = db.search_batch(&[vec![8.0, 6.0, 4.0, 2.0]], 1, Metric::L2, 1).await?;
+
+        sqlx::query("DROP TABLE transform_test").execute(db.write_pool()).await?;
+        assert_eq!(stored, vec![vec![7.0, 3.0], vec![1.0, 0.0]]);
+        assert_eq!(hits[0][0].1, 0.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_search_batch_preserves_order() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let sink = Arc::new(crate::metrics::CountingSink::default());
+        let db = VectorDatabase::new(&url, "search_batch_test", None).await?.with_metrics(sink.clone());
+        db.create_table().await?;
+        for value in 0..10 {
+            db.insert_vector(&[value as f32, -value as f32]).await?;
+        }
+
+        let queries: Vec<Vec<f32>> = (0..100).map(|i| vec![(i % 10) as f32, -(i % 10) as f32]).collect();
+        let results = db.search_batch(&queries, 1, Metric::L2, 100).await?;
+
+        assert_eq!(results.len(), 100);
+        for hits in &results {
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].1, 0.0);
+        }
+        // One record for the whole batch, not one per query.
+        let records = sink.0.lock().unwrap().clone();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].0.as_str(), records[0].1.rows), ("vector", 100));
+
+        sqlx::query("DROP TABLE search_batch_test").execute(db.write_pool()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_insert_batch_returns_ids_in_order() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "insert_batch_test", Some(3)).await?;
+        db.create_table().await?;
+        let vectors: Vec<Vec<f32>> = (0..10_000).map(|i| vec![i as f32, 0.5, -1.0]).collect();
+
+        let ids = db.insert_batch(&vectors).await?;
+        let first: Vec<f32> = sqlx::query_scalar("SELECT vector[1] FROM insert_batch_test WHERE id = $1")
+            .bind(ids[4_321] as i32)
+            .fetch_all(db.write_pool())
+            .await?;
+
+        sqlx::query("DROP TABLE insert_batch_test").execute(db.write_pool()).await?;
+        assert_eq!(ids.len(), 10_000);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(first, vec![4_321.0]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_insert_batch_with_ids_conflict_policies() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "insert_conflict_test", None).await?;
+        db.create_table().await?;
+        db.insert_batch_with_ids(&[(1, vec![1.0])], OnConflict::Error).await?;
+
+        let batch = [(1, vec![10.0]), (2, vec![2.0])];
+        let err = db.insert_batch_with_ids(&batch, OnConflict::Error).await.unwrap_err();
+        assert!(err.to_string().starts_with(r#"batch insert into "insert_conflict_test" failed"#), "{}", err);
+
+        let skipped = db.insert_batch_with_ids(&batch, OnConflict::Skip).await?;
+        assert_eq!(skipped, InsertCounts { inserted: 1, skipped: 1, updated: 0 });
+
+        let batch = [(2, vec![20.0]), (3, vec![3.0])];
+        let updated = db.insert_batch_with_ids(&batch, OnConflict::Update).await?;
+        assert_eq!(updated, InsertCounts { inserted: 1, skipped: 0, updated: 1 });
+
+        sqlx::query("DROP TABLE insert_conflict_test").execute(db.write_pool()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_operation_log_and_replay() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "oplog_test", None).await?.with_operation_log("oplog_test_log")?;
+        db.create_table().await?;
+        db.insert_vector(&[1.5, 1.0]).await?;
+        db.insert_vector(&[2.5, 2.0]).await?;
+
+        // A failed batch rolls back its log entries along with the rows.
+        assert!(db.insert_batch_with_ids(&[(1, vec![9.0, 9.0])], OnConflict::Error).await.is_err());
+        let logged: Vec<(String, i32)> = sqlx::query_as("SELECT op, id FROM oplog_test_log ORDER BY seq")
+            .fetch_all(db.write_pool())
+            .await?;
+
+        let replica = VectorDatabase::new(&url, "oplog_replay_test", None).await?;
+        replica.create_table().await?;
+        let replayed = replica.replay("oplog_test_log").await?;
+        replica.insert_vector(&[3.5, 3.0]).await?;
+        let vectors = replica.query_vectors().await?;
+
+        for table in ["oplog_test", "oplog_test_log", "oplog_replay_test"] {
+            sqlx::query(&format!("DROP TABLE {}", table)).execute(db.write_pool()).await?;
+        }
+        assert_eq!(logged, vec![("insert".to_string(), 1), ("insert".to_string(), 2)]);
+        assert_eq!(replayed, 2);
+        assert_eq!(vectors, vec![vec![1.5, 1.0], vec![2.5, 2.0], vec![3.5, 3.0]]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_rebuild_and_promote() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "promote_test", None).await?;
+        db.create_table().await?;
+        db.insert_batch_with_ids(&[(1, vec![1.0]), (2, vec![2.0])], OnConflict::Error).await?;
+
+        let staging = db.rebuild_into("promote_test_staging").await?;
+        let rows = [(1, vec![10.0]), (2, vec![20.0]), (3, vec![30.0])];
+        staging.insert_batch_with_ids(&rows, OnConflict::Error).await?;
+        db.promote("promote_test_staging").await?;
+
+        let vectors = db.query_vectors().await?;
+        let previous: i64 = sqlx::query_scalar("SELECT count(*) FROM promote_test_previous")
+            .fetch_one(db.write_pool())
+            .await?;
+        let staging_exists: bool = sqlx::query_scalar("SELECT to_regclass('promote_test_staging') IS NOT NULL")
+            .fetch_one(db.write_pool())
+            .await?;
+
+        for table in ["promote_test", "promote_test_previous"] {
+            sqlx::query(&format!("DROP TABLE {}", table)).execute(db.write_pool()).await?;
+        }
+        assert_eq!(vectors, vec![vec![10.0], vec![20.0], vec![30.0]]);
+        assert_eq!(previous, 2);
+        assert!(!staging_exists);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_prune_by_predicate_and_age() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "prune_test", None).await?.prune_batch_size(2);
+        db.create_table().await?;
+        let rows: Vec<(i32, Vec<f32>)> = (1..=5).map(|id| (id, vec![id as f32 - 0.5])).collect();
+        db.insert_batch_with_ids(&rows, OnConflict::Error).await?;
+
+        let pruned = db.prune("vector[1] > $1 AND id <> $2", &[Value::from(1.0), Value::from(4)]).await?;
+        let remaining: Vec<i32> = sqlx::query_scalar("SELECT id FROM prune_test ORDER BY id")
+            .fetch_all(db.write_pool())
+            .await?;
+        let too_old = db.prune_older_than(Utc::now() - chrono::Duration::hours(1)).await?;
+        let all_old = db.prune_older_than(Utc::now() + chrono::Duration::hours(1)).await?;
+
+        sqlx::query("DROP TABLE prune_test").execute(db.write_pool()).await?;
+        assert_eq!(pruned, 3);
+        assert_eq!(remaining, vec![1, 4]);
+        assert_eq!((too_old, all_old), (0, 2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_prune_then_replay() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "prune_replay_test", None).await?
+            .with_operation_log("prune_replay_test_log")?
+            .prune_batch_size(1);
+        db.create_table().await?;
+        let rows: Vec<(i32, Vec<f32>)> = (1..=4).map(|id| (id, vec![id as f32])).collect();
+        db.insert_batch_with_ids(&rows, OnConflict::Error).await?;
+        let pruned = db.prune("id % 2 = $1", &[Value::from(0)]).await?;
+        let logged: Vec<(String, i32)> = sqlx::query_as("SELECT op, id FROM prune_replay_test_log WHERE op = 'delete' ORDER BY id")
+            .fetch_all(db.write_pool())
+            .await?;
+
+        // The replica starts with the rows the prune removed, as one replayed earlier would.
+        let replica = VectorDatabase::new(&url, "prune_replay_target", None).await?;
+        replica.create_table().await?;
+        replica.insert_batch_with_ids(&rows, OnConflict::Error).await?;
+        let replayed = replica.replay("prune_replay_test_log").await?;
+        let vectors = replica.query_vectors().await?;
+
+        for table in ["prune_replay_test", "prune_replay_test_log", "prune_replay_target"] {
+            sqlx::query(&format!("DROP TABLE {}", table)).execute(db.write_pool()).await?;
+        }
+        assert_eq!(pruned, 2);
+        assert_eq!(logged, vec![("delete".to_string(), 2), ("delete".to_string(), 4)]);
+        assert_eq!(replayed, 4);
+        assert_eq!(vectors, vec![vec![1.0], vec![3.0]]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_round_trips_fixed_dimension_vector() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "dimension_test", Some(4)).await?;
+        db.create_table().await?;
+        db.insert_vector(&[0.25, -1.5, 3.0, 1e-3]).await?;
+
+        // The column enforces the dimension too, for writers that bypass this type.
+        let too_short = sqlx::query("INSERT INTO dimension_test (vector) VALUES ($1)")
+            .bind(vec![1.0f32, 2.0])
+            .execute(db.write_pool())
+            .await;
+        let vectors = db.query_vectors().await?;
+
+        sqlx::query("DROP TABLE dimension_test").execute(db.write_pool()).await?;
+        assert!(too_short.is_err());
+        assert_eq!(vectors, vec![vec![0.25, -1.5, 3.0, 1e-3]]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_quoted_mixed_case_table_name() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, r#""MixedCase_Vectors""#, None).await?;
+        db.create_table().await?;
+        db.insert_vector(&[1.0, 2.0]).await?;
+
+        let vectors = db.query_vectors().await?;
+        let exact_name: bool = sqlx::query_scalar("SELECT to_regclass('\"MixedCase_Vectors\"') IS NOT NULL")
+            .fetch_one(db.write_pool())
+            .await?;
+
+        sqlx::query(r#"DROP TABLE "MixedCase_Vectors""#).execute(db.write_pool()).await?;
+        assert_eq!(vectors, vec![vec![1.0, 2.0]]);
+        assert!(exact_name);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_warmup_opens_connections() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let connection = PgConnection {
+            warmup_on_connect: false,
+            ..Default::default()
+        };
+        let db = VectorDatabase::with_connection(&connection, &url, None, "warmup_test").await?;
+        let before = db.write_pool().size();
+
+        db.warmup(3).await?;
+
+        assert!(before < 3);
+        assert_eq!(db.write_pool().size(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_evaluate_recall_without_index_is_exact() -> Result<()> {
+        let url = std::env::var("DATABASE_URL")?;
+        let db = VectorDatabase::new(&url, "recall_test", None).await?;
+        db.create_table().await?;
+        for value in [0.5, 1.0, 1.0, 2.5, 4.0, 8.0] {
+            db.insert_vector(&[value, value * 2.0]).await?;
+        }
+
+        let recall = db.evaluate_recall(4, 2, Metric::L2).await?;
+
+        sqlx::query("DROP TABLE recall_test").execute(db.write_pool()).await?;
+        assert_eq!(recall, 1.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a live Postgres at DATABASE_URL"]
+    async fn test_from_static_credentials() -> Result<()> {
+        use crate::credentials::StaticCredentials;
+
+        let credentials = StaticCredentials::new(&std::env::var("DATABASE_URL")?);
+        let db = VectorDatabase::from_credentials(&credentials, "credentials_test").await?;
+
+        db.create_table().await?;
+        sqlx::query("DROP TABLE credentials_test").execute(db.write_pool()).await?;
+        Ok(())
+    }
 }
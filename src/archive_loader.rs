@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use flate2::read::MultiGzDecoder;
+use glob::{MatchOptions, Pattern};
+use log::info;
+use polars::prelude::*;
+use ::zip::ZipArchive;
+use crate::csv_loader::{CSVLoader, LoaderConfig, LoaderError};
+use crate::metrics::LoadMetrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+fn archive_error(e: impl std::fmt::Display) -> LoaderError {
+    LoaderError::ProcessingError(format!("Failed to read archive: {}", e))
+}
+
+/// Loads CSV entries straight out of a `.zip`, `.tar.gz` or `.tgz` archive, decompressing
+/// each entry in memory rather than extracting it to disk. Every entry is parsed with
+/// `CSVLoader` under the same `LoaderConfig`, except that the metrics sink records each
+/// `load_entry` or `load_matching` call once, as `archive`, rather than every entry.
+pub struct ArchiveLoader {
+    archive_path: PathBuf,
+    format: ArchiveFormat,
+    config: LoaderConfig,
+}
+
+impl ArchiveLoader {
+    pub fn new<P: AsRef<Path>>(archive_path: P, config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
+        let path = archive_path.as_ref();
+        if !path.exists() {
+            return Err(LoaderError::InvalidPath(path.display().to_string()));
+        }
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| LoaderError::InvalidPath(format!(
+            "{} is not a .zip, .tar.gz or .tgz archive",
+            path.display()
+        )))?;
+
+        Ok(ArchiveLoader {
+            archive_path: path.to_path_buf(),
+            format,
+            config: config.unwrap_or_default(),
+        })
+    }
+
+    /// The paths of the file entries in the archive, in archive order.
+    pub fn entries(&self) -> Result<Vec<String>, LoaderError> {
+        let mut names = Vec::new();
+        self.visit(|name, _| {
+            names.push(name.to_string());
+            Ok(())
+        })?;
+        Ok(names)
+    }
+
+    pub fn load_entry(&self, name: &str) -> Result<DataFrame, LoaderError> {
+        let started = Instant::now();
+        let result = self.read_entry(name);
+        self.record_metrics(started, &result, DataFrame::height);
+        result
+    }
+
+    fn read_entry(&self, name: &str) -> Result<DataFrame, LoaderError> {
+        let mut df = None;
+        self.visit(|entry, reader| {
+            if entry == name {
+                df = Some(self.read_csv(reader)?);
+            }
+            Ok(())
+        })?;
+        df.ok_or_else(|| LoaderError::InvalidPath(format!("{} has no entry {}", self.archive_path.display(), name)))
+    }
+
+    /// Loads every entry whose path matches the glob `pattern`, in archive order. `*` stops
+    /// at `/`, so use `**/*.csv` to match at any depth.
+    pub fn load_matching(&self, pattern: &str) -> Result<Vec<(String, DataFrame)>, LoaderError> {
+        let started = Instant::now();
+        let result = self.read_matching(pattern);
+        self.record_metrics(started, &result, |frames| frames.iter().map(|(_, df)| df.height()).sum());
+        result
+    }
+
+    fn read_matching(&self, pattern: &str) -> Result<Vec<(String, DataFrame)>, LoaderError> {
+        let pattern = Pattern::new(pattern)
+            .map_err(|e| LoaderError::ProcessingError(format!("Invalid entry pattern {:?}: {}", pattern, e)))?;
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        let mut frames = Vec::new();
+        self.visit(|name, reader| {
+            if pattern.matches_with(name, options) {
+                frames.push((name.to_string(), self.read_csv(reader)?));
+            }
+            Ok(())
+        })?;
+        info!("Loaded {} entries from {}", frames.len(), self.archive_path.display());
+        Ok(frames)
+    }
+
+    fn read_csv(&self, reader: &mut dyn Read) -> Result<DataFrame, LoaderError> {
+        let config = LoaderConfig { metrics: None, ..self.config.clone() };
+        CSVLoader::from_reader(reader, Some(config))?.load_data()
+    }
+
+    fn record_metrics<T>(&self, started: Instant, result: &Result<T, LoaderError>, rows: impl FnOnce(&T) -> usize) {
+        if let Some(sink) = &self.config.metrics {
+            let bytes = std::fs::metadata(&self.archive_path).ok().map(|m| m.len());
+            sink.record_load("archive", &LoadMetrics::from_result(started, bytes, result, rows));
+        }
+    }
+
+    // Hands every file entry to `f` in archive order, with a reader over its decompressed
+    // contents. Tar has no index, so even a single entry means a pass over the archive.
+    fn visit(&self, mut f: impl FnMut(&str, &mut dyn Read) -> Result<(), LoaderError>) -> Result<(), LoaderError> {
+        let file = File::open(&self.archive_path)?;
+        match self.format {
+            ArchiveFormat::Zip => {
+                let mut archive = ZipArchive::new(file).map_err(archive_error)?;
+                for idx in 0..archive.len() {
+                    let mut entry = archive.by_index(idx).map_err(archive_error)?;
+                    if entry.is_file() {
+                        let name = entry.name().to_string();
+                        f(&name, &mut entry)?;
+                    }
+                }
+            },
+            ArchiveFormat::TarGz => {
+                let mut archive = tar::Archive::new(MultiGzDecoder::new(file));
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if entry.header().entry_type().is_file() {
+                        let name = entry.path()?.to_string_lossy().into_owned();
+                        f(&name, &mut entry)?;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Arc;
+    use crate::metrics::CountingSink;
+    use flate2::write::GzEncoder;
+    use tempfile::tempdir;
+    use ::zip::write::{FileOptions, ZipWriter};
+
+    const ENTRIES: [(&str, &str); 3] = [
+        ("README.txt", "not a csv"),
+        ("data/users.csv", "id,name\n1,alice\n2,bob\n"),
+        ("data/orders.csv", "id,total\n7,9.5\n"),
+    ];
+
+    #[test]
+    fn test_load_entry_from_zip() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("dataset.zip");
+        let mut writer = ZipWriter::new(File::create(&path)?);
+        for (name, contents) in ENTRIES {
+            writer.start_file(name, FileOptions::default())?;
+            writer.write_all(contents.as_bytes())?;
+        }
+        writer.finish()?;
+
+        let loader = ArchiveLoader::new(&path, None)?;
+        let df = loader.load_entry("data/users.csv")?;
+
+        assert_eq!(loader.entries()?, ENTRIES.map(|(name, _)| name.to_string()));
+        let expected = df!("id" => [1i64, 2], "name" => ["alice", "bob"])?;
+        assert!(df.column("id")?.cast(&DataType::Int64)?.equals(expected.column("id")?));
+        assert!(df.column("name")?.cast(&DataType::String)?.equals(expected.column("name")?));
+        assert!(matches!(loader.load_entry("data/missing.csv"), Err(LoaderError::InvalidPath(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_matching_from_tar_gz() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("dataset.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&path)?, Default::default()));
+        for (name, contents) in ENTRIES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, contents.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+
+        let sink = Arc::new(CountingSink::default());
+        let config = LoaderConfig { metrics: Some(sink.clone()), ..Default::default() };
+        let loader = ArchiveLoader::new(&path, Some(config))?;
+        let frames = loader.load_matching("data/*.csv")?;
+
+        let names: Vec<&str> = frames.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["data/users.csv", "data/orders.csv"]);
+        assert_eq!(frames[1].1.column("total")?.cast(&DataType::Float64)?.f64()?.get(0), Some(9.5));
+        assert!(loader.load_matching("*.csv")?.is_empty());
+
+        // One record per call rather than one per entry.
+        let records = sink.0.lock().unwrap();
+        let recorded: Vec<(&str, usize)> = records.iter().map(|(name, m)| (name.as_str(), m.rows)).collect();
+        assert_eq!(recorded, [("archive", 3), ("archive", 0)]);
+        assert_eq!(records[0].1.bytes, Some(std::fs::metadata(&path)?.len()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use polars::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompareOpts {
+    /// Float cells match when `|a - b| <= abs_tolerance + rel_tolerance * max(|a|, |b|)`.
+    /// Integer cells always have to be equal.
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    /// Cell mismatches beyond this are counted but not recorded.
+    pub max_reported: usize,
+}
+
+impl Default for CompareOpts {
+    fn default() -> Self {
+        Self {
+            abs_tolerance: 0.0,
+            rel_tolerance: 0.0,
+            max_reported: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaDiff {
+    MissingInLeft(String),
+    MissingInRight(String),
+    TypeMismatch {
+        column: String,
+        left: DataType,
+        right: DataType,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellDiff {
+    pub column: String,
+    pub row: usize,
+    pub left: String,
+    pub right: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameDiff {
+    pub left_shape: (usize, usize),
+    pub right_shape: (usize, usize),
+    pub schema: Vec<SchemaDiff>,
+    /// The first `max_reported` mismatching cells, in column then row order.
+    pub cells: Vec<CellDiff>,
+    pub mismatched_cells: usize,
+}
+
+impl FrameDiff {
+    pub fn is_equal(&self) -> bool {
+        self.left_shape == self.right_shape && self.schema.is_empty() && self.mismatched_cells == 0
+    }
+}
+
+fn within_tolerance(a: f64, b: f64, opts: &CompareOpts) -> bool {
+    if a.is_nan() || b.is_nan() {
+        return a.is_nan() && b.is_nan();
+    }
+    if a == b {
+        return true;
+    }
+    (a - b).abs() <= opts.abs_tolerance + opts.rel_tolerance * a.abs().max(b.abs())
+}
+
+// Categoricals from separate loads carry different codes for the same string. Integers
+// are widened to one type on both sides rather than to Float64, which can't tell apart
+// values above 2^53; only a float on either side makes the pair Float64.
+fn comparable(series: &Series, other: &DataType) -> PolarsResult<Series> {
+    let dtype = series.dtype();
+    match dtype {
+        DataType::Categorical(..) => series.cast(&DataType::String),
+        _ if dtype.is_unsigned_integer() && other.is_unsigned_integer() => series.cast(&DataType::UInt64),
+        _ if dtype.is_integer() && other.is_integer() => series.cast(&DataType::Int64),
+        _ if dtype.is_numeric() => series.cast(&DataType::Float64),
+        _ => Ok(series.clone()),
+    }
+}
+
+fn cells_match(left: &AnyValue, right: &AnyValue, opts: &CompareOpts) -> bool {
+    match (left, right) {
+        (AnyValue::Float64(a), AnyValue::Float64(b)) => within_tolerance(*a, *b, opts),
+        _ => left == right,
+    }
+}
+
+/// Compares `left` and `right` column by name over the rows both frames have. Float
+/// columns are compared as Float64 with the configured tolerance, everything else,
+/// integers included, exactly.
+pub fn compare_frames(left: &DataFrame, right: &DataFrame, opts: CompareOpts) -> FrameDiff {
+    let mut diff = FrameDiff {
+        left_shape: left.shape(),
+        right_shape: right.shape(),
+        schema: Vec::new(),
+        cells: Vec::new(),
+        mismatched_cells: 0,
+    };
+
+    for name in right.get_column_names() {
+        if left.column(name).is_err() {
+            diff.schema.push(SchemaDiff::MissingInLeft(name.to_string()));
+        }
+    }
+
+    let rows = left.height().min(right.height());
+    for l in left.get_columns() {
+        let Ok(r) = right.column(l.name()) else {
+            diff.schema.push(SchemaDiff::MissingInRight(l.name().to_string()));
+            continue;
+        };
+        if l.dtype() != r.dtype() {
+            diff.schema.push(SchemaDiff::TypeMismatch {
+                column: l.name().to_string(),
+                left: l.dtype().clone(),
+                right: r.dtype().clone(),
+            });
+        }
+
+        let (Ok(lc), Ok(rc)) = (comparable(l, r.dtype()), comparable(r, l.dtype())) else {
+            continue;
+        };
+        for row in 0..rows {
+            let (Ok(a), Ok(b)) = (lc.get(row), rc.get(row)) else {
+                continue;
+            };
+            if cells_match(&a, &b, &opts) {
+                continue;
+            }
+
+            diff.mismatched_cells += 1;
+            if diff.cells.len() < opts.max_reported {
+                diff.cells.push(CellDiff {
+                    column: l.name().to_string(),
+                    row,
+                    left: a.to_string(),
+                    right: b.to_string(),
+                });
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_differences_within_tolerance_are_equal() -> PolarsResult<()> {
+        let left = df!("id" => [1i64, 2, 3], "value" => [1.0, 100.0, f64::NAN], "label" => ["a", "b", "c"])?;
+        let right = df!("id" => [1i32, 2, 3], "value" => [1.0005, 100.05, f64::NAN], "label" => ["a", "b", "c"])?;
+        let opts = CompareOpts {
+            abs_tolerance: 1e-3,
+            rel_tolerance: 1e-3,
+            ..Default::default()
+        };
+
+        let diff = compare_frames(&left, &right, opts);
+
+        // The id width differs, which is a schema difference but not a value one.
+        assert_eq!(diff.mismatched_cells, 0);
+        assert!(matches!(&diff.schema[..], [SchemaDiff::TypeMismatch { column, .. }] if column == "id"));
+        assert!(compare_frames(&left, &left, opts).is_equal());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_integers_compare_exactly() -> PolarsResult<()> {
+        let base = 1i64 << 53;
+        // As Float64, base + 1 rounds to base and u64::MAX - 1 to u64::MAX.
+        let left = df!("id" => [base, base], "count" => [u64::MAX, 1])?;
+        let right = df!("id" => [base, base + 1], "count" => [u64::MAX - 1, 1])?;
+        let opts = CompareOpts {
+            abs_tolerance: 1e-3,
+            rel_tolerance: 1e-3,
+            ..Default::default()
+        };
+
+        let diff = compare_frames(&left, &right, opts);
+
+        assert_eq!(diff.mismatched_cells, 2);
+        assert_eq!((diff.cells[0].column.as_str(), diff.cells[0].row), ("id", 1));
+        assert_eq!(diff.cells[0].right, (base + 1).to_string());
+        assert_eq!((diff.cells[1].column.as_str(), diff.cells[1].row), ("count", 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatches_are_capped() -> PolarsResult<()> {
+        let left = df!("value" => [1.0, 2.0, 3.0, 4.0], "extra" => [0, 0, 0, 0])?;
+        let right = df!("value" => [1.5, 2.5, 3.5, 4.0, 5.0])?;
+        let opts = CompareOpts { max_reported: 2, ..Default::default() };
+
+        let diff = compare_frames(&left, &right, opts);
+
+        assert!(!diff.is_equal());
+        assert_eq!(diff.right_shape, (5, 1));
+        assert_eq!(diff.schema, vec![SchemaDiff::MissingInRight("extra".to_string())]);
+        assert_eq!(diff.mismatched_cells, 3);
+        assert_eq!(diff.cells.len(), 2);
+        assert_eq!((diff.cells[1].row, diff.cells[1].left.as_str()), (1, "2.0"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+use std::time::Duration;
+use futures::future::try_join_all;
+use log::{info, warn};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
+use sqlx::{Pool, Postgres};
+
+/// Pool settings shared by every Postgres-backed loader, so `SQLLoader` and
+/// `VectorDatabase` size, time out and retry their connections the same way.
+#[derive(Debug, Clone)]
+pub struct PgConnection {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    /// Overrides the `sslmode` in the connection string when set.
+    pub ssl_mode: Option<PgSslMode>,
+    pub application_name: Option<String>,
+    /// Server-side `statement_timeout` applied to every connection in the pool.
+    pub statement_timeout: Option<Duration>,
+    /// Extra attempts made when the server can't be reached, with a linear backoff.
+    pub connect_retries: u32,
+    pub retry_backoff: Duration,
+    /// Opens and validates `max_connections` connections as soon as the pool is created,
+    /// so early queries don't pay the connection handshake. Off by default.
+    pub warmup_on_connect: bool,
+}
+
+impl Default for PgConnection {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            ssl_mode: None,
+            application_name: None,
+            statement_timeout: None,
+            connect_retries: 0,
+            retry_backoff: Duration::from_millis(500),
+            warmup_on_connect: false,
+        }
+    }
+}
+
+impl PgConnection {
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+    }
+
+    pub fn connect_options(&self, connection_string: &str) -> Result<PgConnectOptions, sqlx::Error> {
+        let mut options = PgConnectOptions::from_str(connection_string)?;
+        if let Some(ssl_mode) = self.ssl_mode {
+            options = options.ssl_mode(ssl_mode);
+        }
+        if let Some(name) = &self.application_name {
+            options = options.application_name(name);
+        }
+        if let Some(timeout) = self.statement_timeout {
+            options = options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))]);
+        }
+        Ok(options)
+    }
+
+    pub async fn connect(&self, connection_string: &str) -> Result<Pool<Postgres>, sqlx::Error> {
+        let options = self.connect_options(connection_string)?;
+        let mut attempt = 0;
+        loop {
+            match self.pool_options().connect_with(options.clone()).await {
+                Ok(pool) => {
+                    if self.warmup_on_connect {
+                        warmup(&pool, self.max_connections as usize).await?;
+                    }
+                    return Ok(pool);
+                },
+                Err(e) if attempt < self.connect_retries && is_transient(&e) => {
+                    attempt += 1;
+                    warn!("Connecting to Postgres failed ({}), retry {} of {}", e, attempt, self.connect_retries);
+                    tokio::time::sleep(self.retry_backoff * attempt).await;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Builds the pool without opening a connection; the first query connects.
+    pub fn connect_lazy(&self, connection_string: &str) -> Result<Pool<Postgres>, sqlx::Error> {
+        Ok(self.pool_options().connect_lazy_with(self.connect_options(connection_string)?))
+    }
+}
+
+/// Opens up to `n` connections at once (capped at the pool's `max_connections`) and runs
+/// a trivial query on each, leaving them idle in the pool. Returns how many were warmed.
+pub async fn warmup(pool: &Pool<Postgres>, n: usize) -> Result<usize, sqlx::Error> {
+    let n = n.min(pool.options().get_max_connections() as usize);
+    let connections = try_join_all((0..n).map(|_| async {
+        let mut conn = pool.acquire().await?;
+        sqlx::query("SELECT 1").execute(&mut *conn).await?;
+        Ok::<_, sqlx::Error>(conn)
+    }))
+    .await?;
+
+    info!("Warmed {} connections", connections.len());
+    Ok(connections.len())
+}
+
+fn is_transient(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::Tls(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_options_apply_overrides() {
+        let connection = PgConnection {
+            ssl_mode: Some(PgSslMode::Require),
+            application_name: Some("datavolt".to_string()),
+            statement_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+
+        let options = connection.connect_options("postgres://user@db:5432/app?sslmode=disable").unwrap();
+
+        assert!(matches!(options.get_ssl_mode(), PgSslMode::Require));
+        assert_eq!(options.get_application_name(), Some("datavolt"));
+        assert!(options.get_options().unwrap().contains("statement_timeout=5000ms"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use log::info;
+use rusoto_core::Region;
+use rusoto_secretsmanager::{GetSecretValueRequest, SecretsManager, SecretsManagerClient};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+#[derive(Error, Debug)]
+pub enum CredentialError {
+    #[error("Environment variable not set: {0}")]
+    MissingEnv(String),
+    #[error("Failed to fetch secret {secret_id}: {reason}")]
+    SecretFetch { secret_id: String, reason: String },
+}
+
+/// Supplies the connection string a database loader uses when it connects.
+#[async_trait]
+pub trait CredentialSource: Send + Sync {
+    async fn connection_string(&self) -> Result<String, CredentialError>;
+}
+
+pub struct StaticCredentials {
+    connection_string: String,
+}
+
+impl StaticCredentials {
+    pub fn new(connection_string: &str) -> Self {
+        Self {
+            connection_string: connection_string.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialSource for StaticCredentials {
+    async fn connection_string(&self) -> Result<String, CredentialError> {
+        Ok(self.connection_string.clone())
+    }
+}
+
+pub struct EnvCredentials {
+    var: String,
+}
+
+impl EnvCredentials {
+    pub fn new(var: &str) -> Self {
+        Self { var: var.to_string() }
+    }
+}
+
+#[async_trait]
+impl CredentialSource for EnvCredentials {
+    async fn connection_string(&self) -> Result<String, CredentialError> {
+        std::env::var(&self.var).map_err(|_| CredentialError::MissingEnv(self.var.clone()))
+    }
+}
+
+pub struct AwsSecretsManagerCredentials {
+    client: SecretsManagerClient,
+    secret_id: String,
+}
+
+impl AwsSecretsManagerCredentials {
+    pub fn new(secret_id: &str, region: Region) -> Self {
+        Self::with_client(SecretsManagerClient::new(region), secret_id)
+    }
+
+    pub fn with_client(client: SecretsManagerClient, secret_id: &str) -> Self {
+        Self {
+            client,
+            secret_id: secret_id.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialSource for AwsSecretsManagerCredentials {
+    async fn connection_string(&self) -> Result<String, CredentialError> {
+        let request = GetSecretValueRequest {
+            secret_id: self.secret_id.clone(),
+            ..Default::default()
+        };
+
+        let response = self.client.get_secret_value(request).await.map_err(|e| CredentialError::SecretFetch {
+            secret_id: self.secret_id.clone(),
+            reason: e.to_string(),
+        })?;
+
+        response.secret_string.ok_or_else(|| CredentialError::SecretFetch {
+            secret_id: self.secret_id.clone(),
+            reason: "secret has no string value".to_string(),
+        })
+    }
+}
+
+/// Wraps another source and reuses its value until `ttl` has elapsed.
+pub struct CachedCredentials<S> {
+    inner: S,
+    ttl: Duration,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl<S: CredentialSource> CachedCredentials<S> {
+    pub fn new(inner: S, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: CredentialSource> CredentialSource for CachedCredentials<S> {
+    async fn connection_string(&self) -> Result<String, CredentialError> {
+        let mut cached = self.cached.lock().await;
+        if let Some((value, fetched_at)) = cached.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+
+        info!("Refreshing cached credentials");
+        let value = self.inner.connection_string().await?;
+        *cached = Some((value.clone(), Instant::now()));
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher};
+
+    struct CountingCredentials {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CredentialSource for CountingCredentials {
+        async fn connection_string(&self) -> Result<String, CredentialError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("postgres://user@localhost/db".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_credentials() {
+        let source = StaticCredentials::new("postgres://user@localhost/db");
+
+        assert_eq!(source.connection_string().await.unwrap(), "postgres://user@localhost/db");
+    }
+
+    #[tokio::test]
+    async fn test_secrets_manager_credentials() {
+        let dispatcher = MockRequestDispatcher::default()
+            .with_body(r#"{"Name": "db", "SecretString": "postgres://user:secret@db/prod"}"#);
+        let client = SecretsManagerClient::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let source = AwsSecretsManagerCredentials::with_client(client, "db");
+
+        assert_eq!(source.connection_string().await.unwrap(), "postgres://user:secret@db/prod");
+    }
+
+    #[tokio::test]
+    async fn test_cached_credentials_reuse_within_ttl() {
+        let source = CachedCredentials::new(CountingCredentials { calls: AtomicUsize::new(0) }, Duration::from_secs(60));
+
+        source.connection_string().await.unwrap();
+        source.connection_string().await.unwrap();
+
+        assert_eq!(source.inner.calls.load(Ordering::SeqCst), 1);
+    }
+}
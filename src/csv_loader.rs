@@ -1,26 +1,342 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 use std::error::Error;
-use log::{info, error};
+use flate2::read::MultiGzDecoder;
+use log::{info, warn, error};
+use polars::io::csv::read_impl::BatchedCsvReaderRead;
+use polars::io::mmap::MmapBytesReader;
 use polars::prelude::*;
 use rayon::prelude::*;
+use regex::Regex;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
 use sysinfo::{System, SystemExt};
 use thiserror::Error;
+use crate::error::DataVoltError;
+use crate::metrics::{LoadMetrics, MetricsSink};
+use crate::profile::{profile_frame, Profile, ProfileOptions};
+use polars_core::frame::ArrowChunk;
+use polars_core::utils::try_get_supertype;
+use crate::schema::{align_schemas, AlignPolicy};
 
-#[derive(Error, Debug)]
-pub enum LoaderError {
-    #[error("Failed to read CSV file: {0}")]
-    IoError(#[from] std::io::Error),
-    #[error("Failed to process data: {0}")]
-    ProcessingError(String),
-    #[error("Invalid file path: {0}")]
-    InvalidPath(String),
+/// One Arrow chunk per column, all the same length, in schema order.
+pub type RecordBatch = ArrowChunk;
+
+type ChunkTransform<'a> = &'a dyn Fn(DataFrame) -> Result<DataFrame, LoaderError>;
+
+/// Kept under its original name so existing `LoaderError::...` matches still compile.
+pub type LoaderError = DataVoltError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputCompression {
+    /// Picks the codec from the file extension (`.gz`, `.zst`), falling back to the
+    /// magic bytes at the start of the input.
+    #[default]
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl InputCompression {
+    fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" | "gzip" => Some(InputCompression::Gzip),
+            "zst" | "zstd" => Some(InputCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    fn from_magic(header: &[u8]) -> Self {
+        match header {
+            [0x1f, 0x8b, ..] => InputCompression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => InputCompression::Zstd,
+            _ => InputCompression::None,
+        }
+    }
+}
+
+/// Character encoding of the input. Anything other than UTF-8 is transcoded to UTF-8 as
+/// it is read, which means the whole input is read into memory before parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// Windows-1252, the superset of ISO-8859-1 that Windows exports actually use.
+    Latin1,
+    /// Little-endian unless a byte order mark says otherwise.
+    Utf16,
+    /// Follows a byte order mark if there is one. Otherwise UTF-16 is recognised by its
+    /// zero bytes, and input that isn't valid UTF-8 is read as Windows-1252.
+    Auto,
+}
+
+// How many bytes `Encoding::Auto` looks at.
+const ENCODING_SAMPLE_BYTES: usize = 64 * 1024;
+
+impl Encoding {
+    // `None` when the input is already UTF-8 and can be handed to polars as is.
+    fn resolve(self, sample: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+        match self {
+            Encoding::Utf8 => None,
+            Encoding::Latin1 => Some(encoding_rs::WINDOWS_1252),
+            Encoding::Utf16 => Some(encoding_rs::UTF_16LE),
+            Encoding::Auto => sniff_encoding(sample),
+        }
+    }
+}
+
+fn sniff_encoding(sample: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(sample) {
+        return Some(encoding);
+    }
+
+    // Mostly-ASCII UTF-16 has a zero in every other byte.
+    let zeros = |skip: usize| sample.iter().skip(skip).step_by(2).filter(|&&b| b == 0).count();
+    let pairs = sample.len() / 2;
+    if pairs > 0 && zeros(1) * 2 > pairs {
+        return Some(encoding_rs::UTF_16LE);
+    }
+    if pairs > 0 && zeros(0) * 2 > pairs {
+        return Some(encoding_rs::UTF_16BE);
+    }
+
+    // A full sample may end partway through a character.
+    let valid = encoding_rs::Encoding::utf8_valid_up_to(sample);
+    let truncated = sample.len() == ENCODING_SAMPLE_BYTES && sample.len() - valid < 4;
+    if valid == sample.len() || truncated {
+        None
+    } else {
+        Some(encoding_rs::WINDOWS_1252)
+    }
+}
+
+/// Unit of the integers in `LoaderConfig::epoch_columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EpochUnit {
+    #[default]
+    Seconds,
+    Milliseconds,
+    Microseconds,
+}
+
+impl EpochUnit {
+    fn per_second(self) -> i64 {
+        match self {
+            EpochUnit::Seconds => 1,
+            EpochUnit::Milliseconds => 1_000,
+            EpochUnit::Microseconds => 1_000_000,
+        }
+    }
+}
+
+/// What a chunked load does when chunks disagree on their columns, including when a stray
+/// row has more fields than the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaDriftPolicy {
+    Error,
+    /// Gives every chunk the union of the columns. The reader has no column for fields past
+    /// the header, so those are dropped instead, which costs an extra pass over the input
+    /// to find and log them first.
+    Reconcile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    FloatDowncastSkipped,
+    UnknownDefaultColumn,
+    MalformedJson,
+    MalformedCurrency,
+    EpochOutOfRange,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub column: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnConversion {
+    pub column: String,
+    pub from: DataType,
+    pub to: DataType,
+}
+
+/// Collects the warnings raised during a load so callers can inspect them
+/// instead of scraping the log. Every entry is also logged at `warn` level.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    entries: Mutex<Vec<Diagnostic>>,
+    conversions: Mutex<Vec<ColumnConversion>>,
+}
+
+impl Diagnostics {
+    fn warn(&self, code: DiagnosticCode, column: Option<&str>, message: String) {
+        warn!("{}", message);
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Diagnostic {
+                code,
+                message,
+                column: column.map(str::to_string),
+            });
+    }
+
+    pub fn has(&self, code: DiagnosticCode) -> bool {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .any(|d| d.code == code)
+    }
+
+    pub fn into_entries(self) -> Vec<Diagnostic> {
+        self.entries.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Keeps the first conversion seen for each column; chunks are reconciled later.
+    fn converted(&self, column: &str, from: &DataType, to: &DataType) {
+        let mut conversions = self.conversions.lock().unwrap_or_else(PoisonError::into_inner);
+        if !conversions.iter().any(|c| c.column == column) {
+            conversions.push(ColumnConversion {
+                column: column.to_string(),
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+}
+
+/// What a load did, from `CSVLoader::load_data_with_report`.
+#[derive(Debug)]
+pub struct LoadReport {
+    pub rows: usize,
+    /// Chunks read, or 1 for a full-file load.
+    pub chunks: usize,
+    /// Estimated in-memory size of the data as parsed, and after optimization.
+    pub original_bytes: usize,
+    pub optimized_bytes: usize,
+    /// Columns whose type optimization changed, with the type they ended up as.
+    pub conversions: Vec<ColumnConversion>,
+    pub duration: Duration,
+    pub diagnostics: Diagnostics,
+}
+
+/// Row counts from `CSVLoader::load_with_quarantine`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuarantineCounts {
+    pub loaded: usize,
+    pub quarantined: usize,
+}
+
+impl LoadReport {
+    pub fn categorical_columns(&self) -> Vec<&str> {
+        self.conversions
+            .iter()
+            .filter(|c| matches!(c.to, DataType::Categorical(..)))
+            .map(|c| c.column.as_str())
+            .collect()
+    }
 }
 
 #[derive(Clone)]
 pub struct LoaderConfig {
-    reserved_ram_gb: f64,
-    num_workers: usize,
+    pub reserved_ram_gb: f64,
+    /// Threads used to parse chunks in parallel, and to split a full-file read. Zero means
+    /// one per logical CPU.
+    pub num_workers: usize,
+    pub trim_fields: bool,
+    /// Values for fields missing from rows shorter than the header. Nulls the input spells
+    /// out, like an empty field, are kept. Finding the short rows costs one extra pass
+    /// over the input.
+    pub column_defaults: HashMap<String, AnyValue<'static>>,
+    /// Caps how many of polars' threads a read is split across, in place of `num_workers`.
+    /// Polars parses on its own global pool, separate from the `num_workers` chunk workers,
+    /// so peak parallelism is roughly the sum of both. The global pool itself is sized once
+    /// per process, from `POLARS_MAX_THREADS` if it is set before polars is first used.
+    pub polars_threads: Option<usize>,
+    pub json_columns: Vec<String>,
+    /// Sizes chunks so the result and the chunk being parsed fit in this many bytes. Fails
+    /// with `LoaderError::MemoryBudgetExceeded` when the result alone wouldn't fit.
+    pub memory_budget_bytes: Option<u64>,
+    pub schema_drift: SchemaDriftPolicy,
+    /// Casts columns whose type differs between chunks to their common supertype, or to
+    /// String when there is none, instead of failing the concat.
+    pub auto_widen: bool,
+    /// Decodes stray Windows-1252 bytes (smart quotes, dashes) to Unicode and strips
+    /// control characters other than tab and line breaks. The file is read into memory
+    /// to do this, so it only suits files that fit in RAM.
+    pub sanitize_control_chars: bool,
+    /// Casts Float64 columns that hold nulls and only whole numbers back to Int64, so ids
+    /// exported as `1.0` with gaps (the way pandas writes nullable ints) stay exact.
+    pub preserve_nullable_ints: bool,
+    /// Rows per chunk, bypassing the memory-based sizing. `Some(0)` forces a full load.
+    pub chunk_size: Option<usize>,
+    /// Columns holding amounts like `$1,234.56` or `€10`. Symbols and thousands separators
+    /// are stripped and the values parsed as Float64.
+    pub currency_columns: Vec<String>,
+    /// Adds a `<column>_currency` companion column with the symbol found on each value.
+    pub capture_currency_symbol: bool,
+    /// Integer columns holding Unix timestamps in `epoch_unit`, converted to Datetime.
+    /// Values before 1970 or after 2100 are set to null. Unlisted columns are never converted.
+    pub epoch_columns: Vec<String>,
+    pub epoch_unit: EpochUnit,
+    /// Fails the load with `LoaderError::RowLimitExceeded` once more than this many rows
+    /// are read. Reading stops one row past the limit, so the rest of the file is never parsed.
+    pub max_rows: Option<usize>,
+    /// Fails with `LoaderError::ByteLimitExceeded` for inputs larger than this. Files are
+    /// checked before they're read, and `from_reader` stops buffering at the limit.
+    pub max_bytes: Option<u64>,
+    /// Shares categorical codes with every other load holding the same dictionary.
+    pub category_dictionary: Option<CategoryDictionary>,
+    /// Receives duration, row and byte counts after every `load_data` call.
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+    pub compression: InputCompression,
+    pub encoding: Encoding,
+    /// How many times larger than its on-disk size a compressed file is assumed to be
+    /// once decompressed, when sizing chunks against available memory.
+    pub compressed_inflation: f64,
+    /// Types the reader uses for these columns instead of inferring them, e.g. `String` for
+    /// zero-padded ids. Pinned columns are never narrowed or made categorical afterwards,
+    /// and win over a sidecar schema.
+    pub schema_overrides: Option<HashMap<String, DataType>>,
+    /// Reads only these columns; the rest are skipped while parsing.
+    pub columns: Option<Vec<String>>,
+    pub delimiter: u8,
+    /// `None` turns quoting off, so quote characters are kept as data.
+    pub quote_char: Option<u8>,
+    /// Without a header row columns are named `column_1`, `column_2`, ...
+    pub has_header: bool,
+    /// Tokens read as null in every column, e.g. `NULL` or `\N`.
+    pub null_values: Option<Vec<String>>,
+    /// String columns whose share of distinct values is below this become categorical.
+    /// Zero turns the conversion off.
+    pub categorical_threshold: f64,
+    /// Narrows Float64 columns to Float32 when every value fits. Turn off where the lost
+    /// precision matters, e.g. money.
+    pub downcast_floats: bool,
+    /// Narrows Int64 columns to the smallest integer type that holds every value.
+    pub downcast_ints: bool,
+    /// Columns whose names match are identifiers and skip both downcasting and the
+    /// categorical conversion, so they stay Int64 or String and join cleanly across files.
+    /// Defaults to `id` and `*_id`, any case; `None` optimizes them like any other column.
+    pub id_column_pattern: Option<Regex>,
+}
+
+impl LoaderConfig {
+    /// `num_workers`, with zero resolved to the number of logical CPUs.
+    pub fn worker_count(&self) -> usize {
+        match self.num_workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
 }
 
 impl Default for LoaderConfig {
@@ -28,155 +344,3484 @@ impl Default for LoaderConfig {
         Self {
             reserved_ram_gb: 2.0,
             num_workers: 7,
+            trim_fields: false,
+            column_defaults: HashMap::new(),
+            polars_threads: None,
+            json_columns: Vec::new(),
+            memory_budget_bytes: None,
+            schema_drift: SchemaDriftPolicy::Error,
+            auto_widen: false,
+            sanitize_control_chars: false,
+            preserve_nullable_ints: false,
+            chunk_size: None,
+            currency_columns: Vec::new(),
+            capture_currency_symbol: false,
+            epoch_columns: Vec::new(),
+            epoch_unit: EpochUnit::Seconds,
+            max_rows: None,
+            max_bytes: None,
+            category_dictionary: None,
+            metrics: None,
+            compression: InputCompression::Auto,
+            encoding: Encoding::Utf8,
+            compressed_inflation: 5.0,
+            schema_overrides: None,
+            columns: None,
+            delimiter: b',',
+            quote_char: Some(b'"'),
+            has_header: true,
+            null_values: None,
+            categorical_threshold: 0.5,
+            downcast_floats: true,
+            downcast_ints: true,
+            id_column_pattern: Some(Regex::new(DEFAULT_ID_COLUMN_PATTERN).expect("valid id column pattern")),
         }
     }
 }
 
-pub struct CSVLoader {
-    file_path: PathBuf,
-    config: LoaderConfig,
+/// Keeps polars' global string cache enabled while any clone is alive, so categorical
+/// columns built by separate loads encode the same string with the same integer code.
+#[derive(Clone)]
+pub struct CategoryDictionary {
+    _cache: Arc<StringCacheHolder>,
 }
 
-impl CSVLoader {
-    pub fn new<P: AsRef<Path>>(file_path: P, config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
-        let file_path = file_path.as_ref().to_path_buf();
-        if !file_path.exists() {
-            return Err(LoaderError::InvalidPath(
-                file_path.to_string_lossy().to_string()
-            ));
-        }
+impl CategoryDictionary {
+    pub fn new() -> Self {
+        Self { _cache: Arc::new(StringCacheHolder::hold()) }
+    }
 
-        Ok(Self {
-            file_path,
-            config: config.unwrap_or_default(),
-        })
+    /// Registers `categories` up front so they take the lowest codes, in the order given,
+    /// regardless of which file mentions them first. This only holds if nothing else has
+    /// filled the global cache yet.
+    pub fn with_categories(categories: &[&str]) -> Result<Self, LoaderError> {
+        let dictionary = Self::new();
+        let _ = Series::new("categories", categories)
+            .cast(&DataType::Categorical(None))
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        Ok(dictionary)
+    }
+}
+
+impl Default for CategoryDictionary {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn calculate_chunk_size(&self, file_size: u64) -> usize {
-        let sys = System::new_all();
-        let total_ram_gb = sys.total_memory() as f64 / (1024.0 * 1024.0 * 1024.0);
-        let available_ram_gb = total_ram_gb - self.config.reserved_ram_gb;
+// Largest magnitude below which every integer is exactly representable as f64.
+const MAX_EXACT_F64_INT: f64 = 9_007_199_254_740_992.0;
 
-        let estimated_df_size_gb = (file_size as f64 * 1.5) / (1024.0 * 1024.0 * 1024.0);
+// 2100-01-01T00:00:00Z. Integers past this are far more likely ids or counters than timestamps.
+const MAX_PLAUSIBLE_EPOCH_SECS: i64 = 4_102_444_800;
 
-        if estimated_df_size_gb < available_ram_gb {
-            0
-        } else {
-            let chunk_size = ((available_ram_gb * 0.25 * 1024.0 * 1024.0) / estimated_df_size_gb) as usize;
-            chunk_size.max(1000)
-        }
+const DEFAULT_ID_COLUMN_PATTERN: &str = "(?i)^(id|.+_id)$";
+
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥', '₹', '₩', '₽', '₪', '₺', '₫', '฿', '¢'];
+
+/// Parses an amount such as `$1,234.56`, `-€10` or `(£5.00)`, returning the value and
+/// the currency symbol it carried, if any.
+fn parse_currency(raw: &str) -> Option<(f64, Option<char>)> {
+    let mut text = raw.trim();
+    let mut negative = false;
+    if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        negative = true;
+        text = inner.trim();
     }
 
-    fn optimize_chunk(df: &mut DataFrame) -> Result<(), LoaderError> {
-        for column_name in df.get_column_names() {
-            let column = df.column(column_name).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+    let symbol = text.chars().find(|c| CURRENCY_SYMBOLS.contains(c));
+    let digits: String = text.chars()
+        .filter(|c| !CURRENCY_SYMBOLS.contains(c) && *c != ',' && !c.is_whitespace())
+        .collect();
+    let value: f64 = digits.parse().ok()?;
 
-            match column.dtype() {
-                DataType::String => {
-                    let unique_ratio = column.n_unique().map_err(|e| LoaderError::ProcessingError(e.to_string()))? as f64
-                        / column.len() as f64;
-                    if unique_ratio < 0.5 {
-                        df.try_apply(column_name, |s| s.cast(&DataType::Categorical(None)))
-                            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+    Some((if negative { -value } else { value }, symbol))
+}
+
+fn windows_1252_char(byte: u8) -> Option<char> {
+    let c = match byte {
+        0x80 => '€', 0x82 => '‚', 0x83 => 'ƒ', 0x84 => '„', 0x85 => '…', 0x86 => '†',
+        0x87 => '‡', 0x88 => 'ˆ', 0x89 => '‰', 0x8A => 'Š', 0x8B => '‹', 0x8C => 'Œ',
+        0x8E => 'Ž', 0x91 => '‘', 0x92 => '’', 0x93 => '“', 0x94 => '”', 0x95 => '•',
+        0x96 => '–', 0x97 => '—', 0x98 => '˜', 0x99 => '™', 0x9A => 'š', 0x9B => '›',
+        0x9C => 'œ', 0x9E => 'ž', 0x9F => 'Ÿ',
+        0xA0..=0xFF => char::from(byte),
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn push_printable(out: &mut String, text: &str) {
+    out.extend(text.chars().filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r')));
+}
+
+fn sanitize_bytes(input: &[u8]) -> Vec<u8> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                push_printable(&mut out, valid);
+                break;
+            },
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                push_printable(&mut out, std::str::from_utf8(valid).expect("prefix is valid UTF-8"));
+
+                let bad_len = e.error_len().unwrap_or(invalid.len());
+                for &byte in &invalid[..bad_len] {
+                    if let Some(c) = windows_1252_char(byte) {
+                        out.push(c);
                     }
-                },
-                DataType::Float64 => {
-                    df.try_apply(column_name, |s| s.cast(&DataType::Float32))
-                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
-                },
-                DataType::Int64 => {
-                    let min = column.min::<i64>().unwrap_or(i64::MAX);
-                    let max = column.max::<i64>().unwrap_or(i64::MIN);
+                }
+                rest = &invalid[bad_len..];
+            },
+        }
+    }
+    out.into_bytes()
+}
 
-                    let new_type = if min >= 0 {
-                        if max <= u8::MAX as i64 { DataType::UInt8 }
-                        else if max <= u16::MAX as i64 { DataType::UInt16 }
-                        else if max <= u32::MAX as i64 { DataType::UInt32 }
-                        else { DataType::UInt64 }
-                    } else {
-                        if min >= i8::MIN as i64 && max <= i8::MAX as i64 { DataType::Int8 }
-                        else if min >= i16::MIN as i64 && max <= i16::MAX as i64 { DataType::Int16 }
-                        else if min >= i32::MIN as i64 && max <= i32::MAX as i64 { DataType::Int32 }
-                        else { DataType::Int64 }
-                    };
+/// Rows per chunk for an input of `input_bytes`, or zero to load it in one go, from
+/// `config`'s chunk size, memory budget or the RAM left over. `sample` opens the input as
+/// one row per line and is only read when a memory budget needs a row size.
+pub(crate) fn chunk_size_for<R: Read>(
+    config: &LoaderConfig,
+    input_bytes: u64,
+    sample: impl FnOnce() -> Result<R, LoaderError>,
+) -> Result<usize, LoaderError> {
+    if input_bytes == 0 {
+        return Ok(0);
+    }
+    if let Some(chunk_size) = config.chunk_size {
+        return Ok(chunk_size);
+    }
+    if let Some(budget) = config.memory_budget_bytes {
+        return chunk_size_for_budget(budget, input_bytes, sample);
+    }
 
-                    df.try_apply(column_name, |s| s.cast(&new_type))
-                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+    let sys = System::new_all();
+    let total_ram_gb = sys.total_memory() as f64 / (1024.0 * 1024.0 * 1024.0);
+    let available_ram_gb = total_ram_gb - config.reserved_ram_gb;
+
+    let estimated_df_size_gb = (input_bytes as f64 * 1.5) / (1024.0 * 1024.0 * 1024.0);
+
+    if estimated_df_size_gb < available_ram_gb {
+        Ok(0)
+    } else {
+        let chunk_size = ((available_ram_gb * 0.25 * 1024.0 * 1024.0) / estimated_df_size_gb) as usize;
+        Ok(chunk_size.max(1000))
+    }
+}
+
+fn chunk_size_for_budget<R: Read>(
+    budget: u64,
+    file_size: u64,
+    sample: impl FnOnce() -> Result<R, LoaderError>,
+) -> Result<usize, LoaderError> {
+    let estimated_df_size = file_size as f64 * 1.5;
+
+    // Peak usage is the accumulated frame plus the chunk being parsed; a full load
+    // parses the whole file while the accumulator is the whole file as well.
+    if estimated_df_size * 2.0 <= budget as f64 {
+        return Ok(0);
+    }
+
+    let row_size = estimate_row_bytes(sample()?, file_size)? * 1.5;
+    let headroom = budget as f64 - estimated_df_size;
+    if headroom < row_size {
+        return Err(LoaderError::MemoryBudgetExceeded { budget, estimated: estimated_df_size as u64 });
+    }
+
+    Ok((headroom / row_size) as usize)
+}
+
+fn estimate_row_bytes(reader: impl Read, file_size: u64) -> Result<f64, LoaderError> {
+    let mut sample = Vec::new();
+    reader.take(64 * 1024).read_to_end(&mut sample)?;
+
+    let lines = sample.iter().filter(|&&b| b == b'\n').count();
+    if lines == 0 {
+        return Ok(file_size.max(1) as f64);
+    }
+    Ok(sample.len() as f64 / lines as f64)
+}
+
+#[derive(Error, Debug)]
+#[error("Input is not valid {encoding}: malformed byte sequence at byte {offset}")]
+pub struct DecodeError {
+    pub encoding: &'static str,
+    pub offset: u64,
+}
+
+/// Transcodes `inner` to UTF-8 as it is read. Malformed input fails the read with a
+/// `DecodeError` rather than being replaced.
+struct Utf8Transcoder<R> {
+    inner: R,
+    decoder: encoding_rs::Decoder,
+    encoding: &'static str,
+    input: Box<[u8]>,
+    input_range: std::ops::Range<usize>,
+    output: Box<[u8]>,
+    output_range: std::ops::Range<usize>,
+    consumed: u64,
+    eof: bool,
+    finished: bool,
+}
+
+impl<R: Read> Utf8Transcoder<R> {
+    fn new(inner: R, encoding: &'static encoding_rs::Encoding) -> Self {
+        Self {
+            inner,
+            // Sniffs a byte order mark, which may override `encoding`, and drops it.
+            decoder: encoding.new_decoder(),
+            encoding: encoding.name(),
+            input: vec![0; 64 * 1024].into_boxed_slice(),
+            input_range: 0..0,
+            output: vec![0; 64 * 1024].into_boxed_slice(),
+            output_range: 0..0,
+            consumed: 0,
+            eof: false,
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if !self.output_range.is_empty() {
+                let n = buf.len().min(self.output_range.len());
+                buf[..n].copy_from_slice(&self.output[self.output_range.start..self.output_range.start + n]);
+                self.output_range.start += n;
+                return Ok(n);
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            if self.input_range.is_empty() && !self.eof {
+                let read = self.inner.read(&mut self.input)?;
+                self.input_range = 0..read;
+                self.eof = read == 0;
+            }
+
+            let (result, read, written) = self.decoder.decode_to_utf8_without_replacement(
+                &self.input[self.input_range.clone()],
+                &mut self.output,
+                self.eof,
+            );
+            self.input_range.start += read;
+            self.consumed += read as u64;
+            self.output_range = 0..written;
+            match result {
+                encoding_rs::DecoderResult::InputEmpty => self.finished = self.eof,
+                encoding_rs::DecoderResult::OutputFull => {},
+                encoding_rs::DecoderResult::Malformed(bad, after) => {
+                    let offset = self.consumed - after as u64 - bad as u64;
+                    let error = DecodeError { encoding: self.encoding, offset };
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error));
                 },
-                _ => {}
             }
         }
-        Ok(())
     }
+}
 
-    pub fn load_data(&self) -> Result<DataFrame, LoaderError> {
-        let file_size = std::fs::metadata(&self.file_path)?.len();
-        let chunk_size = self.calculate_chunk_size(file_size);
+/// Keeps the bytes read from `inner` since the window's `start`, so rows can be inspected while the csv
+/// reader streams past them. The owner drops what it no longer needs with `discard_before`.
+struct RecentBytes<R> {
+    inner: R,
+    window: std::rc::Rc<std::cell::RefCell<ByteWindow>>,
+}
 
-        info!("Loading CSV with chunk size: {}", if chunk_size > 0 { chunk_size.to_string() } else { "Full file".to_string() });
+#[derive(Default)]
+struct ByteWindow {
+    start: u64,
+    bytes: Vec<u8>,
+}
 
-        if chunk_size == 0 {
-            let mut df = CsvReader::from_path(&self.file_path)
-                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?
-                .finish()
-                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+impl ByteWindow {
+    fn get(&self, offset: u64) -> u8 {
+        self.bytes[(offset - self.start) as usize]
+    }
 
-            Self::optimize_chunk(&mut df)?;
-            info!("Successfully loaded data with shape: {:?}", df.shape());
-            Ok(df)
-        } else {
-            let file_path = Arc::new(self.file_path.clone());
-            let chunks: Result<Vec<DataFrame>, LoaderError> = (0..)
-                .into_par_iter()
-                .map(|chunk_idx| {
-                    let offset = chunk_idx * chunk_size;
-                    let mut reader = CsvReader::from_path(file_path.as_ref())
-                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?
-                        .with_chunk_size(chunk_size)
-                        .finish()
-                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+    fn discard_before(&mut self, offset: u64) {
+        self.bytes.drain(..(offset - self.start) as usize);
+        self.start = offset;
+    }
+}
 
-                    match reader.nth(chunk_idx) {
-                        Some(chunk_result) => {
-                            let mut chunk = chunk_result.map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
-                            Self::optimize_chunk(&mut chunk)?;
-                            Ok(chunk)
-                        },
-                        None => Err(LoaderError::ProcessingError("No more chunks".to_string())),
-                    }
-                })
-                .take_while(|result| !matches!(result, Err(LoaderError::ProcessingError(e)) if e == "No more chunks"))
-                .collect();
+impl<R: Read> Read for RecentBytes<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.window.borrow_mut().bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
 
-            let df = concat(chunks?.as_slice(), true)
-                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+/// A Rust type a single CSV column can be extracted as with [`CSVLoader::load_column`].
+/// `T` rejects nulls; `Option<T>` keeps them.
+pub trait ColumnElement: Sized {
+    fn from_series(series: &Series) -> Result<Vec<Self>, LoaderError>;
+}
 
-            info!("Successfully loaded data with shape: {:?}", df.shape());
-            Ok(df)
+// SplitMix64: small, seedable and fixed, so a seed picks the same rows in every release.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn shuffle<T>(items: &mut [T], state: &mut u64) {
+    for i in (1..items.len()).rev() {
+        let j = (splitmix64(state) % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+// Polars' batched reader can report the end of the input early, when the rest of the file
+// is shorter than one batch but the read that fetched it didn't reach EOF. Asking once more
+// returns the remainder; at the real end it returns `None` again.
+fn next_batches(batches: &mut BatchedCsvReaderRead, n: usize) -> Result<Option<Vec<DataFrame>>, LoaderError> {
+    match batches.next_batches(n).map_err(|e| LoaderError::ProcessingError(e.to_string()))? {
+        Some(frames) => Ok(Some(frames)),
+        None => batches.next_batches(n).map_err(|e| LoaderError::ProcessingError(e.to_string())),
+    }
+}
+
+fn typed_column(series: &Series, target: &DataType) -> Result<Series, LoaderError> {
+    let source = series.dtype();
+    let compatible = if target.is_numeric() {
+        source.is_numeric()
+    } else if target == &DataType::String {
+        matches!(source, DataType::String | DataType::Categorical(..))
+    } else {
+        source == target
+    };
+    if !compatible {
+        return Err(LoaderError::ProcessingError(format!(
+            "Column '{}' has type {}, which can't be read as {}",
+            series.name(),
+            source,
+            target
+        )));
+    }
+
+    let typed = series.strict_cast(target).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+    // A strict cast only rejects values that overflow, so `10.5` would quietly become `10`.
+    // Casting back shows whether any value lost precision on the way.
+    if target.is_numeric() && source != target {
+        let round_trip = typed.cast(source).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        if !round_trip.equals_missing(series) {
+            return Err(LoaderError::ProcessingError(format!(
+                "Column '{}' has values that can't be read as {} without losing precision",
+                series.name(),
+                target
+            )));
         }
     }
+    Ok(typed)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::NamedTempFile;
-    use std::io::Write;
+fn reject_nulls<T>(name: &str, values: Vec<Option<T>>) -> Result<Vec<T>, LoaderError> {
+    values.into_iter()
+        .collect::<Option<Vec<T>>>()
+        .ok_or_else(|| LoaderError::ProcessingError(format!("Column '{}' contains nulls", name)))
+}
 
-    #[test]
-    fn test_csv_loader() -> Result<(), Box<dyn Error>> {
-        let mut file = NamedTempFile::new()?;
-        writeln!(file, "id,value,category")?;
-        writeln!(file, "1,10.5,A")?;
-        writeln!(file, "2,20.7,B")?;
-        writeln!(file, "3,30.2,A")?;
+macro_rules! impl_column_element {
+    ($ty:ty, $dtype:expr, $accessor:ident, $map:expr) => {
+        impl ColumnElement for Option<$ty> {
+            fn from_series(series: &Series) -> Result<Vec<Self>, LoaderError> {
+                let typed = typed_column(series, &$dtype)?;
+                let values = typed.$accessor().map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                Ok(values.into_iter().map(|v| v.map($map)).collect())
+            }
+        }
 
-        let loader = CSVLoader::new(file.path(), None)?;
+        impl ColumnElement for $ty {
+            fn from_series(series: &Series) -> Result<Vec<Self>, LoaderError> {
+                reject_nulls(series.name(), <Option<$ty>>::from_series(series)?)
+            }
+        }
+    };
+}
 
-        let df = loader.load_data()?;
+impl_column_element!(f64, DataType::Float64, f64, |v| v);
+impl_column_element!(f32, DataType::Float32, f32, |v| v);
+impl_column_element!(i64, DataType::Int64, i64, |v| v);
+impl_column_element!(i32, DataType::Int32, i32, |v| v);
+impl_column_element!(bool, DataType::Boolean, bool, |v| v);
+impl_column_element!(String, DataType::String, str, str::to_string);
 
-        assert_eq!(df.shape(), (3, 3));
-        assert_eq!(df.column("id")?.len(), 3);
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaDocument {
+    pub columns: Vec<ColumnSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    pub name: String,
+    pub dtype: String,
+    pub nullable: bool,
+}
+
+fn dtype_to_name(dtype: &DataType) -> Result<&'static str, LoaderError> {
+    let name = match dtype {
+        DataType::Boolean => "Boolean",
+        DataType::UInt8 => "UInt8",
+        DataType::UInt16 => "UInt16",
+        DataType::UInt32 => "UInt32",
+        DataType::UInt64 => "UInt64",
+        DataType::Int8 => "Int8",
+        DataType::Int16 => "Int16",
+        DataType::Int32 => "Int32",
+        DataType::Int64 => "Int64",
+        DataType::Float32 => "Float32",
+        DataType::Float64 => "Float64",
+        DataType::String => "String",
+        DataType::Categorical(..) => "Categorical",
+        DataType::Date => "Date",
+        other => return Err(LoaderError::ProcessingError(format!("Unsupported dtype in schema: {}", other))),
+    };
+    Ok(name)
+}
+
+fn dtype_from_name(name: &str) -> Result<DataType, LoaderError> {
+    let dtype = match name {
+        "Boolean" => DataType::Boolean,
+        "UInt8" => DataType::UInt8,
+        "UInt16" => DataType::UInt16,
+        "UInt32" => DataType::UInt32,
+        "UInt64" => DataType::UInt64,
+        "Int8" => DataType::Int8,
+        "Int16" => DataType::Int16,
+        "Int32" => DataType::Int32,
+        "Int64" => DataType::Int64,
+        "Float32" => DataType::Float32,
+        "Float64" => DataType::Float64,
+        "String" => DataType::String,
+        "Categorical" => DataType::Categorical(None),
+        "Date" => DataType::Date,
+        other => return Err(LoaderError::ProcessingError(format!("Unknown dtype in schema: {}", other))),
+    };
+    Ok(dtype)
+}
+
+pub fn read_schema_json<P: AsRef<Path>>(path: P) -> Result<Schema, LoaderError> {
+    let contents = std::fs::read_to_string(path)?;
+    let document: SchemaDocument = serde_json::from_str(&contents)
+        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+    document.columns
+        .iter()
+        .map(|column| Ok(Field::new(&column.name, dtype_from_name(&column.dtype)?)))
+        .collect()
+}
+
+/// Options for `CSVLoader::scan_builder`.
+pub struct CsvScan<'a> {
+    loader: &'a CSVLoader,
+    optimize: bool,
+}
+
+impl CsvScan<'_> {
+    /// Applies the categorical and Float32 casts `load_data` makes. Those depend on the
+    /// data, which a lazy scan hasn't read, so they're chosen from the first
+    /// `SCAN_SAMPLE_ROWS` rows; a later Float64 value outside Float32 range becomes infinite.
+    pub fn with_optimizations(mut self, enabled: bool) -> Self {
+        self.optimize = enabled;
+        self
+    }
+
+    pub fn finish(self) -> Result<LazyFrame, LoaderError> {
+        let loader = self.loader;
+        let lazy = match &loader.source {
+            CsvSource::File(path) if !loader.reads_into_memory() => {
+                loader.check_file_row_limit()?;
+                let lazy = LazyCsvReader::new(path)
+                    .with_separator(loader.config.delimiter)
+                    .with_quote_char(loader.config.quote_char)
+                    .has_header(loader.config.has_header)
+                    .with_null_values(loader.null_values())
+                    .with_dtype_overwrite(loader.dtypes.as_deref())
+                    .finish()
+                    .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                match &loader.config.columns {
+                    Some(columns) => lazy.select(columns.iter().map(|name| col(name)).collect::<Vec<_>>()),
+                    None => lazy,
+                }
+            },
+            _ => {
+                let df = loader.open_reader()?
+                    .finish()
+                    .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                loader.check_row_limit(df.height())?;
+                df.lazy()
+            },
+        };
+        if !self.optimize {
+            return Ok(lazy);
+        }
+
+        let mut sample = loader.open_reader()?
+            .with_n_rows(Some(SCAN_SAMPLE_ROWS))
+            .finish()
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        let original = sample.schema();
+        loader.optimize_chunk(&mut sample, &Diagnostics::default())?;
+
+        let casts: Vec<Expr> = sample.get_columns()
+            .iter()
+            .filter(|series| original.get(series.name()) != Some(series.dtype()))
+            .map(|series| {
+                // Casts need a bare categorical type, not the sample's own mapping.
+                let dtype = match series.dtype() {
+                    DataType::Categorical(..) => DataType::Categorical(None),
+                    dtype => dtype.clone(),
+                };
+                col(series.name()).cast(dtype)
+            })
+            .collect();
+        Ok(lazy.with_columns(casts))
+    }
+}
+
+const SCAN_SAMPLE_ROWS: usize = 1000;
+pub const STREAM_BATCH_ROWS: usize = 100_000;
+// Polars' default inference window, so a quarantining load infers what `load_data` would.
+const INFER_SCHEMA_ROWS: usize = 128;
+
+// Sidecar types first, then `schema_overrides` on top so the config always wins.
+fn declared_dtypes(sidecar: Option<Schema>, config: &LoaderConfig) -> Option<SchemaRef> {
+    let mut schema = sidecar.unwrap_or_default();
+    for (name, dtype) in config.schema_overrides.iter().flatten() {
+        schema.with_column(name.as_str().into(), dtype.clone());
+    }
+    (!schema.is_empty()).then(|| Arc::new(schema))
+}
+
+enum CsvSource {
+    File(PathBuf),
+    Buffer(Arc<[u8]>),
+}
+
+// Rows with fewer fields than the header, so column defaults fill only the values a row
+// left out rather than every null.
+struct MissingFields {
+    // Each column's position in the input.
+    positions: HashMap<String, usize>,
+    // The data row index and field count of every short row, in row order.
+    short_rows: Vec<(usize, usize)>,
+}
+
+impl MissingFields {
+    // Which of the `len` rows from `first_row` on lack the field at `position`.
+    fn mask(&self, position: usize, first_row: usize, len: usize) -> BooleanChunked {
+        let mut missing = vec![false; len];
+        let start = self.short_rows.partition_point(|&(row, _)| row < first_row);
+        for &(row, fields) in self.short_rows[start..].iter().take_while(|&&(row, _)| row < first_row + len) {
+            missing[row - first_row] = fields <= position;
+        }
+        BooleanChunked::from_slice("missing", &missing)
+    }
+}
+
+pub struct CSVLoader {
+    source: CsvSource,
+    // Resolved at construction, never `Auto`.
+    compression: InputCompression,
+    // What the input is transcoded from, resolved at construction. `None` for UTF-8.
+    decoding: Option<&'static encoding_rs::Encoding>,
+    dtypes: Option<SchemaRef>,
+    config: LoaderConfig,
+    thread_pool: OnceLock<ThreadPool>,
+}
+
+impl CSVLoader {
+    pub fn new<P: AsRef<Path>>(file_path: P, config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
+        let file_path = file_path.as_ref().to_path_buf();
+        if !file_path.exists() {
+            return Err(LoaderError::InvalidPath(
+                file_path.to_string_lossy().to_string()
+            ));
+        }
+
+        let config = config.unwrap_or_default();
+        let compression = match config.compression {
+            InputCompression::Auto => match InputCompression::from_extension(&file_path) {
+                Some(compression) => compression,
+                None => {
+                    let mut header = Vec::new();
+                    std::fs::File::open(&file_path)?.take(4).read_to_end(&mut header)?;
+                    InputCompression::from_magic(&header)
+                },
+            },
+            compression => compression,
+        };
+
+        let mut loader = Self {
+            source: CsvSource::File(file_path),
+            compression,
+            decoding: None,
+            dtypes: declared_dtypes(None, &config),
+            config,
+            thread_pool: OnceLock::new(),
+        };
+        loader.decoding = loader.detect_encoding()?;
+        Ok(loader)
+    }
+
+    /// Types columns from a sidecar schema next to the file, `data.csv` -> `data.schema.json`,
+    /// instead of inferring them, and leaves those columns out of the categorical and
+    /// Float32 optimizations. The sidecar uses the format `export_schema_json` writes:
+    ///
+    /// ```json
+    /// {"columns": [{"name": "id", "dtype": "String", "nullable": false}]}
+    /// ```
+    ///
+    /// Columns the sidecar doesn't list are still inferred. Without a sidecar, or for
+    /// in-memory input, the loader is returned unchanged.
+    pub fn with_sidecar_schema(mut self) -> Result<Self, LoaderError> {
+        if let CsvSource::File(path) = &self.source {
+            let sidecar = path.with_extension("schema.json");
+            if sidecar.exists() {
+                self.dtypes = declared_dtypes(Some(read_schema_json(&sidecar)?), &self.config);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Reads CSV from the process's stdin, whether it is a pipe or a redirected file.
+    pub fn from_stdin(config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
+        Self::from_reader(std::io::stdin().lock(), config)
+    }
+
+    /// Buffers everything `reader` yields and loads from memory. The input size isn't known
+    /// up front, so the data is loaded in full unless `LoaderConfig::chunk_size` is set.
+    pub fn from_reader<R: Read>(reader: R, config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
+        let config = config.unwrap_or_default();
+        let mut bytes = Vec::new();
+        let limit = config.max_bytes.map_or(u64::MAX, |max| max.saturating_add(1));
+        reader.take(limit).read_to_end(&mut bytes)?;
+        if let Some(max) = config.max_bytes.filter(|&max| bytes.len() as u64 > max) {
+            return Err(LoaderError::ByteLimitExceeded(max));
+        }
+
+        let compression = match config.compression {
+            InputCompression::Auto => InputCompression::from_magic(&bytes),
+            compression => compression,
+        };
+
+        let mut loader = Self {
+            source: CsvSource::Buffer(bytes.into()),
+            compression,
+            decoding: None,
+            dtypes: declared_dtypes(None, &config),
+            config,
+            thread_pool: OnceLock::new(),
+        };
+        loader.decoding = loader.detect_encoding()?;
+        Ok(loader)
+    }
+
+    fn check_byte_limit(&self) -> Result<(), LoaderError> {
+        let Some(max) = self.config.max_bytes else {
+            return Ok(());
+        };
+        let size = match &self.source {
+            CsvSource::File(path) => std::fs::metadata(path)?.len(),
+            CsvSource::Buffer(bytes) => bytes.len() as u64,
+        };
+        if size > max {
+            return Err(LoaderError::ByteLimitExceeded(max));
+        }
+        Ok(())
+    }
+
+    fn check_row_limit(&self, rows: usize) -> Result<(), LoaderError> {
+        match self.config.max_rows {
+            Some(max) if rows > max => Err(LoaderError::RowLimitExceeded(max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.compression != InputCompression::None
+    }
+
+    fn detect_encoding(&self) -> Result<Option<&'static encoding_rs::Encoding>, LoaderError> {
+        let mut sample = Vec::new();
+        if self.config.encoding == Encoding::Auto {
+            self.decompressed_reader()?.take(ENCODING_SAMPLE_BYTES as u64).read_to_end(&mut sample)?;
+        }
+        Ok(self.config.encoding.resolve(&sample))
+    }
+
+    /// The input as UTF-8 text, decompressed and transcoded.
+    fn raw_reader(&self) -> Result<Box<dyn Read + '_>, LoaderError> {
+        self.check_byte_limit()?;
+        let reader = self.decompressed_reader()?;
+        Ok(match self.decoding {
+            Some(encoding) => Box::new(Utf8Transcoder::new(reader, encoding)),
+            None => reader,
+        })
+    }
+
+    // Files concatenated from several gzip members are common, and a single-member decoder
+    // would stop silently after the first one.
+    fn decompressed_reader(&self) -> Result<Box<dyn Read + '_>, LoaderError> {
+        let reader: Box<dyn Read + '_> = match &self.source {
+            CsvSource::File(path) => Box::new(std::fs::File::open(path)?),
+            CsvSource::Buffer(bytes) => Box::new(&bytes[..]),
+        };
+        Ok(match self.compression {
+            InputCompression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            InputCompression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+            InputCompression::Auto | InputCompression::None => reader,
+        })
+    }
+
+    /// Byte offset just past the first row over `max_rows`, found with a streaming scan so
+    /// the rest of the input is never read. `None` when there's no limit.
+    fn row_limit_offset(&self) -> Result<Option<u64>, LoaderError> {
+        let Some(max) = self.config.max_rows else {
+            return Ok(None);
+        };
+        Ok(Some(self.read_past_row_limit(max)?.1))
+    }
+
+    // Polars reads a file in large pieces and may parse well past `with_n_rows`, so before
+    // it opens a row-limited file the rows are counted with the csv crate, stopping one row
+    // past the limit. Buffered input is cut at the limit instead.
+    fn check_file_row_limit(&self) -> Result<(), LoaderError> {
+        match self.config.max_rows {
+            Some(max) if !self.reads_into_memory() => self.check_row_limit(self.read_past_row_limit(max)?.0),
+            _ => Ok(()),
+        }
+    }
+
+    // Reads the header and up to one row past `max`, returning the data rows read and
+    // the byte offset they end at.
+    fn read_past_row_limit(&self, max: usize) -> Result<(usize, u64), LoaderError> {
+        let mut reader = self.record_reader().from_reader(self.raw_reader()?);
+        let header = self.config.has_header as usize;
+
+        let mut record = csv::ByteRecord::new();
+        let mut records = 0;
+        while records < max.saturating_add(1 + header) {
+            if !reader.read_byte_record(&mut record).map_err(|e| LoaderError::ProcessingError(e.to_string()))? {
+                break;
+            }
+            records += 1;
+        }
+        Ok((records.saturating_sub(header), reader.position().byte()))
+    }
+
+    // A zero-byte input, which polars rejects outright rather than reading as no rows.
+    fn is_empty_input(&self) -> Result<bool, LoaderError> {
+        Ok(self.raw_reader()?.read(&mut [0u8; 1])? == 0)
+    }
+
+    // Whether anything follows the header. Polars' batched reader panics on header-only files.
+    fn has_data_rows(&self) -> Result<bool, LoaderError> {
+        let mut reader = self.record_reader().from_reader(self.raw_reader()?);
+
+        let mut record = csv::ByteRecord::new();
+        for _ in 0..1 + self.config.has_header as usize {
+            if !reader.read_byte_record(&mut record).map_err(|e| LoaderError::ProcessingError(e.to_string()))? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // A csv-crate reader that splits records the way polars will. The header, if any, is
+    // returned as an ordinary record.
+    fn record_reader(&self) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.config.delimiter)
+            .quoting(self.config.quote_char.is_some());
+        if let Some(quote) = self.config.quote_char {
+            builder.quote(quote);
+        }
+        builder
+    }
+
+    fn null_values(&self) -> Option<NullValues> {
+        self.config.null_values.clone().map(NullValues::AllColumns)
+    }
+
+    /// Whether `open_reader` hands polars an in-memory buffer rather than the file itself.
+    fn reads_into_memory(&self) -> bool {
+        !matches!(self.source, CsvSource::File(_))
+            || self.is_compressed()
+            || self.config.sanitize_control_chars
+            || self.decoding.is_some()
+    }
+
+    fn open_reader(&self) -> Result<CsvReader<'static, Box<dyn MmapBytesReader>>, LoaderError> {
+        self.check_byte_limit()?;
+        let rewritten = self.is_compressed() || self.config.sanitize_control_chars || self.decoding.is_some();
+        let reader: Box<dyn MmapBytesReader> = match &self.source {
+            CsvSource::File(path) if !self.reads_into_memory() => {
+                self.check_file_row_limit()?;
+                Box::new(std::fs::File::open(path)?)
+            },
+            CsvSource::Buffer(bytes) if !rewritten && self.config.max_rows.is_none() => {
+                Box::new(Cursor::new(bytes.clone()))
+            },
+            _ => {
+                // Only what the row limit lets through is decompressed and transcoded.
+                let limit = self.row_limit_offset()?;
+                let mut bytes = Vec::new();
+                self.raw_reader()?.take(limit.unwrap_or(u64::MAX)).read_to_end(&mut bytes)?;
+                if self.config.sanitize_control_chars {
+                    bytes = sanitize_bytes(&bytes);
+                }
+                Box::new(Cursor::new(bytes))
+            },
+        };
+        Ok(CsvReader::new(reader)
+            .with_separator(self.config.delimiter)
+            .with_quote_char(self.config.quote_char)
+            .has_header(self.config.has_header)
+            .with_null_values(self.null_values())
+            .with_dtypes(self.dtypes.clone())
+            .with_columns(self.config.columns.clone())
+            .with_n_threads(Some(self.reader_threads()))
+            // One row past the limit, so callers counting rows see buffered input exceed it.
+            .with_n_rows(self.config.max_rows.map(|max| max.saturating_add(1))))
+    }
+
+    /// The chunk workers' pool, sized to `num_workers` and built on first use so repeated
+    /// loads share it. Two threads racing here may both build a pool, but only one is kept.
+    fn thread_pool(&self) -> Result<&ThreadPool, LoaderError> {
+        if let Some(pool) = self.thread_pool.get() {
+            return Ok(pool);
+        }
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.config.worker_count())
+            .build()
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        Ok(self.thread_pool.get_or_init(|| pool))
+    }
+
+    fn reader_threads(&self) -> usize {
+        self.config.polars_threads.unwrap_or_else(|| self.config.worker_count()).max(1)
+    }
+
+    fn calculate_chunk_size(&self, file_size: u64) -> Result<usize, LoaderError> {
+        chunk_size_for(&self.config, file_size, || self.raw_reader())
+    }
+
+    fn column_names(df: &DataFrame) -> Vec<String> {
+        df.get_column_names().iter().map(|name| name.to_string()).collect()
+    }
+
+    fn check_chunk_columns(chunks: &mut [DataFrame], policy: SchemaDriftPolicy) -> Result<(), LoaderError> {
+        let Some(first) = chunks.first() else {
+            return Ok(());
+        };
+        let expected = Self::column_names(first);
+
+        let drift = chunks.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(idx, chunk)| (idx, Self::column_names(chunk)))
+            .find(|(_, found)| *found != expected);
+
+        if let Some((chunk, found)) = drift {
+            error!("Chunk {} has columns {:?}, expected {:?}", chunk, found, expected);
+            match policy {
+                SchemaDriftPolicy::Error => return Err(LoaderError::SchemaDrift { chunk, expected, found }),
+                SchemaDriftPolicy::Reconcile => {
+                    align_schemas(chunks, AlignPolicy::Union)
+                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// The first row with more fields than the header, as the chunk it falls in with the
+    /// header's columns and the columns that row would have, named the way polars names
+    /// columns it finds no header for.
+    fn find_extra_fields(&self, chunk_size: usize) -> Result<Option<(usize, Vec<String>, Vec<String>)>, LoaderError> {
+        let csv_error = |e: csv::Error| LoaderError::ProcessingError(e.to_string());
+        let mut reader = self.record_reader().from_reader(self.raw_reader()?);
+        let mut record = csv::ByteRecord::new();
+
+        let mut expected = Vec::new();
+        if self.config.has_header && reader.read_byte_record(&mut record).map_err(csv_error)? {
+            expected = record.iter().map(|name| String::from_utf8_lossy(name).into_owned()).collect();
+        }
+
+        let mut row = 0;
+        while row <= self.config.max_rows.unwrap_or(usize::MAX) && reader.read_byte_record(&mut record).map_err(csv_error)? {
+            if row == 0 && !self.config.has_header {
+                expected = (1..=record.len()).map(|position| format!("column_{}", position)).collect();
+            }
+            if record.len() > expected.len() {
+                let mut found = expected.clone();
+                found.extend((expected.len() + 1..=record.len()).map(|position| format!("column_{}", position)));
+                return Ok(Some((row / chunk_size.max(1), expected, found)));
+            }
+            row += 1;
+        }
+        Ok(None)
+    }
+
+    // Polars fails on a row wider than the header without saying where it is, so once a
+    // chunked read fails the input is searched for one and reported as drift instead.
+    fn explain_read_error(&self, error: LoaderError, chunk_size: usize) -> LoaderError {
+        match self.find_extra_fields(chunk_size) {
+            Ok(Some((chunk, expected, found))) => {
+                error!("Chunk {} has columns {:?}, expected {:?}", chunk, found, expected);
+                LoaderError::SchemaDrift { chunk, expected, found }
+            },
+            _ => error,
+        }
+    }
+
+    // Chunks are optimized independently, so a column can narrow to UInt8 in one chunk and
+    // UInt16 in the next, or turn categorical in only some of them. Those are brought back in
+    // line here; any other mismatch is left to `auto_widen`.
+    fn align_optimized_types(chunks: &mut [DataFrame]) -> Result<(), LoaderError> {
+        let Some(first) = chunks.first() else {
+            return Ok(());
+        };
+
+        let names: Vec<String> = first.get_column_names().iter().map(|name| name.to_string()).collect();
+        for name in names {
+            let dtypes: Vec<DataType> = chunks.iter()
+                .filter_map(|chunk| chunk.column(&name).ok().map(|s| s.dtype().clone()))
+                .collect();
+            if dtypes.iter().all(|dtype| dtype == &dtypes[0]) {
+                continue;
+            }
+
+            let target = if dtypes.iter().all(DataType::is_numeric) {
+                dtypes.iter().skip(1).try_fold(dtypes[0].clone(), |acc, dtype| try_get_supertype(&acc, dtype).ok())
+            } else if dtypes.iter().all(|dtype| matches!(dtype, DataType::String | DataType::Categorical(..))) {
+                Some(DataType::String)
+            } else {
+                None
+            };
+            let Some(target) = target else { continue };
+
+            for chunk in chunks.iter_mut() {
+                if chunk.column(&name).map_or(false, |s| s.dtype() != &target) {
+                    chunk.try_apply(&name, |s| s.cast(&target))
+                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn widen_chunk_types(chunks: &mut [DataFrame]) -> Result<(), LoaderError> {
+        let Some(first) = chunks.first() else {
+            return Ok(());
+        };
+
+        let mut widened: Vec<(String, DataType)> = Vec::new();
+        for series in first.get_columns() {
+            let name = series.name();
+            let mut target = series.dtype().clone();
+            for chunk in chunks.iter().skip(1) {
+                let Ok(other) = chunk.column(name) else { continue };
+                if other.dtype() != &target {
+                    target = try_get_supertype(&target, other.dtype()).unwrap_or(DataType::String);
+                }
+            }
+            if chunks.iter().any(|chunk| chunk.column(name).map_or(false, |s| s.dtype() != &target)) {
+                widened.push((name.to_string(), target));
+            }
+        }
+
+        for (name, target) in &widened {
+            let found: Vec<String> = chunks.iter()
+                .filter_map(|chunk| chunk.column(name).ok().map(|s| s.dtype().to_string()))
+                .collect();
+            info!("Widening column '{}' from {:?} to {}", name, found, target);
+
+            for chunk in chunks.iter_mut() {
+                if chunk.column(name).map_or(false, |s| s.dtype() != target) {
+                    chunk.try_apply(name, |s| s.cast(target))
+                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn trim_string_fields(df: &mut DataFrame) -> Result<(), LoaderError> {
+        let string_columns: Vec<String> = df.get_columns()
+            .iter()
+            .filter(|s| s.dtype() == &DataType::String)
+            .map(|s| s.name().to_string())
+            .collect();
+
+        // A null pattern strips whitespace, matching `str.strip_chars()` with no argument.
+        let whitespace = Series::new_null("", 1);
+        for column_name in &string_columns {
+            df.try_apply(column_name, |s| {
+                s.str()?.strip_chars(&whitespace).map(|ca| ca.into_series())
+            })
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Finds the rows shorter than the header with a streaming pass over the input.
+    fn missing_fields(&self) -> Result<MissingFields, LoaderError> {
+        let csv_error = |e: csv::Error| LoaderError::ProcessingError(e.to_string());
+        let mut reader = self.record_reader().from_reader(self.raw_reader()?);
+        let mut record = csv::ByteRecord::new();
+
+        let mut positions = HashMap::new();
+        if self.config.has_header && reader.read_byte_record(&mut record).map_err(csv_error)? {
+            for (position, name) in record.iter().enumerate() {
+                positions.insert(String::from_utf8_lossy(name).into_owned(), position);
+            }
+        }
+        let mut width = positions.len();
+
+        let mut short_rows = Vec::new();
+        let mut row = 0;
+        // Rows past the limit fail the load anyway.
+        while row <= self.config.max_rows.unwrap_or(usize::MAX) && reader.read_byte_record(&mut record).map_err(csv_error)? {
+            // Without a header, polars takes the width from the first row.
+            if row == 0 && !self.config.has_header {
+                width = record.len();
+                positions.extend((0..width).map(|position| (format!("column_{}", position + 1), position)));
+            }
+            if record.len() < width {
+                short_rows.push((row, record.len()));
+            }
+            row += 1;
+        }
+        Ok(MissingFields { positions, short_rows })
+    }
+
+    // `first_row` is the index among the input's data rows of the frame's first row.
+    fn fill_column_defaults(
+        df: &mut DataFrame,
+        defaults: &HashMap<String, AnyValue<'static>>,
+        missing: &MissingFields,
+        first_row: usize,
+        diagnostics: &Diagnostics,
+    ) -> Result<(), LoaderError> {
+        for (column_name, default) in defaults {
+            let (Ok(_), Some(&position)) = (df.column(column_name), missing.positions.get(column_name)) else {
+                diagnostics.warn(
+                    DiagnosticCode::UnknownDefaultColumn,
+                    Some(column_name),
+                    format!("Default given for unknown column '{}', skipping", column_name),
+                );
+                continue;
+            };
+
+            let mask = missing.mask(position, first_row, df.height());
+            if !mask.any() {
+                continue;
+            }
+            df.try_apply(column_name, |s| {
+                let fill = Series::from_any_values(s.name(), &[default.clone()], false)?
+                    .cast(s.dtype())?
+                    .new_from_index(0, s.len());
+                s.zip_with(&!&mask, &fill)
+            })
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn parse_currency_columns(
+        df: &mut DataFrame,
+        columns: &[String],
+        capture_symbol: bool,
+        diagnostics: &Diagnostics,
+    ) -> Result<(), LoaderError> {
+        for column_name in columns {
+            let raw = df.column(column_name)
+                .and_then(|s| s.cast(&DataType::String))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            let raw = raw.str().map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+            let mut amounts = Vec::with_capacity(raw.len());
+            let mut symbols = Vec::with_capacity(raw.len());
+            let mut malformed = 0;
+            for value in raw.into_iter() {
+                match value.map(parse_currency) {
+                    Some(Some((amount, symbol))) => {
+                        amounts.push(Some(amount));
+                        symbols.push(symbol.map(String::from));
+                    },
+                    Some(None) => {
+                        malformed += 1;
+                        amounts.push(None);
+                        symbols.push(None);
+                    },
+                    None => {
+                        amounts.push(None);
+                        symbols.push(None);
+                    },
+                }
+            }
+
+            if malformed > 0 {
+                diagnostics.warn(
+                    DiagnosticCode::MalformedCurrency,
+                    Some(column_name),
+                    format!("{} unparseable amounts in column '{}' set to null", malformed, column_name),
+                );
+            }
+
+            df.with_column(Series::new(column_name, amounts))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            if capture_symbol {
+                df.with_column(Series::new(&format!("{}_currency", column_name), symbols))
+                    .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_epoch_columns(
+        df: &mut DataFrame,
+        columns: &[String],
+        unit: EpochUnit,
+        diagnostics: &Diagnostics,
+    ) -> Result<(), LoaderError> {
+        let max = MAX_PLAUSIBLE_EPOCH_SECS * unit.per_second();
+        for column_name in columns {
+            let column = df.column(column_name)
+                .map_err(|_| LoaderError::MissingColumn(column_name.clone()))?;
+            // A chunk where the column is entirely empty is inferred as String.
+            if !column.dtype().is_integer() && column.null_count() != column.len() {
+                return Err(LoaderError::ProcessingError(format!(
+                    "Epoch column '{}' is {}, expected integers",
+                    column_name,
+                    column.dtype()
+                )));
+            }
+
+            let values = column.cast(&DataType::Int64)
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            let values = values.i64().map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            let in_range: Int64Chunked = values.into_iter()
+                .map(|v| v.filter(|v| (0..=max).contains(v)))
+                .collect();
+
+            let out_of_range = in_range.null_count() - values.null_count();
+            if out_of_range > 0 {
+                diagnostics.warn(
+                    DiagnosticCode::EpochOutOfRange,
+                    Some(column_name),
+                    format!("{} values in column '{}' outside 1970-2100 set to null", out_of_range, column_name),
+                );
+            }
+
+            // Polars has no seconds unit, so seconds are scaled up to milliseconds.
+            let (in_range, time_unit) = match unit {
+                EpochUnit::Seconds => (in_range * 1_000, TimeUnit::Milliseconds),
+                EpochUnit::Milliseconds => (in_range, TimeUnit::Milliseconds),
+                EpochUnit::Microseconds => (in_range, TimeUnit::Microseconds),
+            };
+            let mut datetimes = in_range.into_series()
+                .cast(&DataType::Datetime(time_unit, None))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            datetimes.rename(column_name);
+            df.replace(column_name, datetimes)
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn parse_json_columns(df: &mut DataFrame, columns: &[String], diagnostics: &Diagnostics) -> Result<(), LoaderError> {
+        for column_name in columns {
+            let values = df.column(column_name)
+                .and_then(|s| s.str())
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+            // Re-encode every cell as an NDJSON record so polars infers the List/Struct type.
+            let mut lines = String::new();
+            let mut malformed = 0;
+            for value in values.into_iter() {
+                let parsed = match value.map(serde_json::from_str::<serde_json::Value>) {
+                    Some(Ok(parsed)) => parsed,
+                    Some(Err(_)) => {
+                        malformed += 1;
+                        serde_json::Value::Null
+                    },
+                    None => serde_json::Value::Null,
+                };
+                lines.push_str(&serde_json::json!({ "value": parsed }).to_string());
+                lines.push('\n');
+            }
+
+            if malformed > 0 {
+                diagnostics.warn(
+                    DiagnosticCode::MalformedJson,
+                    Some(column_name),
+                    format!("{} malformed JSON values in column '{}' set to null", malformed, column_name),
+                );
+            }
+
+            let parsed = JsonReader::new(Cursor::new(lines))
+                .with_json_format(JsonFormat::JsonLines)
+                .finish()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            let mut series = parsed.column("value")
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?
+                .clone();
+            series.rename(column_name);
+            df.replace(column_name, series)
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    // The short rows, when there are column defaults to fill them with.
+    fn missing_fields_for_defaults(&self) -> Result<Option<MissingFields>, LoaderError> {
+        if self.config.column_defaults.is_empty() {
+            return Ok(None);
+        }
+        self.missing_fields().map(Some)
+    }
+
+    // `first_row` is the index among the input's data rows of the frame's first row, which
+    // lines the frame up with `missing`.
+    fn prepare_chunk(
+        &self,
+        df: &mut DataFrame,
+        first_row: usize,
+        missing: Option<&MissingFields>,
+        diagnostics: &Diagnostics,
+    ) -> Result<(), LoaderError> {
+        if self.config.trim_fields {
+            Self::trim_string_fields(df)?;
+        }
+        if let Some(missing) = missing {
+            Self::fill_column_defaults(df, &self.config.column_defaults, missing, first_row, diagnostics)?;
+        }
+        if !self.config.json_columns.is_empty() {
+            Self::parse_json_columns(df, &self.config.json_columns, diagnostics)?;
+        }
+        if !self.config.currency_columns.is_empty() {
+            Self::parse_currency_columns(df, &self.config.currency_columns, self.config.capture_currency_symbol, diagnostics)?;
+        }
+        if self.config.preserve_nullable_ints {
+            Self::restore_nullable_ints(df)?;
+        }
+        if !self.config.epoch_columns.is_empty() {
+            Self::parse_epoch_columns(df, &self.config.epoch_columns, self.config.epoch_unit, diagnostics)?;
+        }
+        self.optimize_chunk(df, diagnostics)
+    }
+
+    fn restore_nullable_ints(df: &mut DataFrame) -> Result<(), LoaderError> {
+        let candidates: Vec<String> = df.get_columns()
+            .iter()
+            .filter(|s| s.dtype() == &DataType::Float64 && s.null_count() > 0)
+            .filter(|s| {
+                s.f64().map_or(false, |ca| {
+                    ca.into_iter()
+                        .flatten()
+                        .all(|v| v.fract() == 0.0 && v.abs() <= MAX_EXACT_F64_INT)
+                })
+            })
+            .map(|s| s.name().to_string())
+            .collect();
+
+        for name in candidates {
+            df.try_apply(&name, |s| s.cast(&DataType::Int64))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    // Declared columns, from a sidecar schema or `schema_overrides`, keep their type.
+    fn optimize_chunk(&self, df: &mut DataFrame, diagnostics: &Diagnostics) -> Result<(), LoaderError> {
+        let declared = self.dtypes.as_deref();
+        for column_name in df.get_column_names() {
+            let column = df.column(column_name).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            if declared.is_some_and(|schema| schema.contains(column.name())) {
+                continue;
+            }
+            if self.config.id_column_pattern.as_ref().is_some_and(|pattern| pattern.is_match(column_name)) {
+                continue;
+            }
+
+            match column.dtype() {
+                DataType::String => {
+                    let unique_ratio = column.n_unique().map_err(|e| LoaderError::ProcessingError(e.to_string()))? as f64
+                        / column.len() as f64;
+                    if unique_ratio < self.config.categorical_threshold {
+                        df.try_apply(column_name, |s| s.cast(&DataType::Categorical(None)))
+                            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                        diagnostics.converted(column_name, &DataType::String, &DataType::Categorical(None));
+                    }
+                },
+                DataType::Float64 if self.config.downcast_floats => {
+                    let max_abs = column.f64()
+                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?
+                        .into_iter()
+                        .flatten()
+                        .filter(|v| v.is_finite())
+                        .fold(0.0, |acc: f64, v| acc.max(v.abs()));
+
+                    if max_abs > f32::MAX as f64 {
+                        diagnostics.warn(
+                            DiagnosticCode::FloatDowncastSkipped,
+                            Some(column_name),
+                            format!("Column '{}' exceeds Float32 range, keeping Float64", column_name),
+                        );
+                    } else {
+                        df.try_apply(column_name, |s| s.cast(&DataType::Float32))
+                            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                        diagnostics.converted(column_name, &DataType::Float64, &DataType::Float32);
+                    }
+                },
+                DataType::Int64 if self.config.downcast_ints => {
+                    let min = column.min::<i64>().unwrap_or(i64::MAX);
+                    let max = column.max::<i64>().unwrap_or(i64::MIN);
+
+                    let new_type = if min >= 0 {
+                        if max <= u8::MAX as i64 { DataType::UInt8 }
+                        else if max <= u16::MAX as i64 { DataType::UInt16 }
+                        else if max <= u32::MAX as i64 { DataType::UInt32 }
+                        else { DataType::UInt64 }
+                    } else {
+                        if min >= i8::MIN as i64 && max <= i8::MAX as i64 { DataType::Int8 }
+                        else if min >= i16::MIN as i64 && max <= i16::MAX as i64 { DataType::Int16 }
+                        else if min >= i32::MIN as i64 && max <= i32::MAX as i64 { DataType::Int32 }
+                        else { DataType::Int64 }
+                    };
+
+                    df.try_apply(column_name, |s| s.cast(&new_type))
+                        .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                    if new_type != DataType::Int64 {
+                        diagnostics.converted(column_name, &DataType::Int64, &new_type);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a lazy plan over the file without reading it, so filters and projections are
+    /// pushed down into the scan. Nothing is loaded until the caller runs `.collect()`.
+    /// Inputs that `load_data` would buffer in memory (compressed, sanitized or read from a
+    /// stream) can't be scanned lazily and are read up front. With `max_rows` set, the rows
+    /// are counted before the plan is returned, stopping one past the limit.
+    pub fn scan(&self) -> Result<LazyFrame, LoaderError> {
+        self.scan_builder().finish()
+    }
+
+    pub fn scan_builder(&self) -> CsvScan<'_> {
+        CsvScan { loader: self, optimize: false }
+    }
+
+    /// Runs a group-by aggregation out-of-core with polars' streaming engine, so the
+    /// file never has to fit in memory. Only streaming-compatible expressions (sum, min,
+    /// max, mean, count, first/last) are supported; anything else makes polars fall back
+    /// to the in-memory engine for that part of the plan.
+    pub fn aggregate_streaming(&self, by: &[&str], aggs: &[Expr]) -> Result<DataFrame, LoaderError> {
+        self.check_byte_limit()?;
+        let keys: Vec<Expr> = by.iter().map(|name| col(name)).collect();
+
+        let df = self.scan()?
+            .group_by(keys)
+            .agg(aggs)
+            .with_streaming(true)
+            .collect()
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+        info!("Streaming aggregation produced shape: {:?}", df.shape());
+        Ok(df)
+    }
+
+    pub fn export_schema_json<P: AsRef<Path>>(&self, path: P) -> Result<(), LoaderError> {
+        let df = self.load_data()?;
+
+        let columns = df.get_columns()
+            .iter()
+            .map(|s| Ok(ColumnSpec {
+                name: s.name().to_string(),
+                dtype: dtype_to_name(s.dtype())?.to_string(),
+                nullable: s.null_count() > 0,
+            }))
+            .collect::<Result<Vec<_>, LoaderError>>()?;
+
+        let json = serde_json::to_string_pretty(&SchemaDocument { columns })
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        std::fs::write(path, json)?;
+
+        info!("Exported schema for {} columns", df.width());
+        Ok(())
+    }
+
+    /// Checks the loaded data against a schema in the format `export_schema_json` writes.
+    /// Fails with `LoaderError::SchemaViolation` listing every column that is missing, not
+    /// in the schema, holding values its type can't hold exactly, or holding nulls the
+    /// schema doesn't allow. The schema records optimized types, so a column narrowed
+    /// differently, or left String instead of Categorical, still passes if its values fit.
+    pub fn validate_schema<P: AsRef<Path>>(&self, path: P) -> Result<(), LoaderError> {
+        let contents = std::fs::read_to_string(path)?;
+        let document: SchemaDocument = serde_json::from_str(&contents)
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        let df = self.load_data()?;
+
+        let mut violations = Vec::new();
+        for spec in &document.columns {
+            let Ok(column) = df.column(&spec.name) else {
+                violations.push(format!("column '{}' is missing", spec.name));
+                continue;
+            };
+            let target = match dtype_from_name(&spec.dtype)? {
+                DataType::Categorical(..) => DataType::String,
+                dtype => dtype,
+            };
+            if typed_column(column, &target).is_err() {
+                let dtype = dtype_to_name(column.dtype()).map_or_else(|_| column.dtype().to_string(), str::to_string);
+                violations.push(format!("column '{}' is {}, expected {}", spec.name, dtype, spec.dtype));
+            }
+            if !spec.nullable && column.null_count() > 0 {
+                violations.push(format!("column '{}' has {} nulls but isn't nullable", spec.name, column.null_count()));
+            }
+        }
+        for name in df.get_column_names() {
+            if !document.columns.iter().any(|spec| spec.name == name) {
+                violations.push(format!("column '{}' isn't in the schema", name));
+            }
+        }
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        warn!("Schema validation found {} problems", violations.len());
+        Err(LoaderError::SchemaViolation(violations))
+    }
+
+    fn ensure_columns(df: &DataFrame, names: &[&str]) -> Result<(), LoaderError> {
+        match names.iter().find(|name| df.column(name).is_err()) {
+            Some(name) => Err(LoaderError::MissingColumn(name.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load_melted(&self, id_vars: &[&str], value_vars: &[&str]) -> Result<DataFrame, LoaderError> {
+        let df = self.load_data()?;
+        Self::ensure_columns(&df, id_vars)?;
+        Self::ensure_columns(&df, value_vars)?;
+
+        df.melt(id_vars, value_vars)
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))
+    }
+
+    pub fn load_pivoted(&self, index: &[&str], columns: &[&str], values: &[&str]) -> Result<DataFrame, LoaderError> {
+        let df = self.load_data()?;
+        Self::ensure_columns(&df, index)?;
+        Self::ensure_columns(&df, columns)?;
+        Self::ensure_columns(&df, values)?;
+
+        pivot::pivot_stable(&df, values, index, columns, false, Some(col("").first()), None)
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))
+    }
+
+    pub fn for_each_row(&self, mut f: impl FnMut(&[AnyValue])) -> Result<usize, LoaderError> {
+        if self.is_empty_input()? {
+            return Ok(0);
+        }
+
+        let mut row_count = 0;
+        let mut visit = |df: DataFrame| -> Result<(), LoaderError> {
+            let columns = df.get_columns();
+            let mut row = Vec::with_capacity(columns.len());
+            for idx in 0..df.height() {
+                self.check_row_limit(row_count + 1)?;
+                row.clear();
+                for column in columns {
+                    row.push(column.get(idx).map_err(|e| LoaderError::ProcessingError(e.to_string()))?);
+                }
+                f(&row);
+                row_count += 1;
+            }
+            Ok(())
+        };
+
+        let mut reader = self.open_reader()?;
+        if self.reads_into_memory() {
+            // Batched reads need a file-backed reader, and this input is already in memory.
+            visit(reader.finish().map_err(|e| LoaderError::ProcessingError(e.to_string()))?)?;
+        } else {
+            let mut batches = reader.batched_borrowed_read()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            while let Some(frames) = next_batches(&mut batches, 1)? {
+                for df in frames {
+                    visit(df)?;
+                }
+            }
+        }
+
+        info!("Visited {} rows", row_count);
+        Ok(row_count)
+    }
+
+    /// Copies the `select` columns, in that order, to `output`, renaming any listed in
+    /// `rename`. Rows are streamed one at a time without building a DataFrame, so memory
+    /// stays flat regardless of file size. Returns the number of rows written.
+    pub fn transform_to(
+        &self,
+        output: &Path,
+        select: &[&str],
+        rename: &HashMap<String, String>,
+    ) -> Result<usize, LoaderError> {
+        let csv_error = |e: csv::Error| LoaderError::ProcessingError(e.to_string());
+
+        let mut reader = self.record_reader().flexible(false).from_reader(self.raw_reader()?);
+        let mut record = csv::ByteRecord::new();
+        // Without a header the first record is only peeked, so it's still read as data below.
+        let first = reader.headers().map_err(csv_error)?.clone();
+        let headers: Vec<String> = if self.config.has_header {
+            reader.read_byte_record(&mut record).map_err(csv_error)?;
+            first.iter().map(str::to_string).collect()
+        } else {
+            (1..=first.len()).map(|idx| format!("column_{}", idx)).collect()
+        };
+        let indices = select.iter()
+            .map(|name| {
+                headers.iter()
+                    .position(|h| h == *name)
+                    .ok_or_else(|| LoaderError::MissingColumn(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut writer = csv::Writer::from_path(output).map_err(csv_error)?;
+        writer.write_record(select.iter().map(|name| rename.get(*name).map_or(*name, String::as_str)))
+            .map_err(csv_error)?;
+
+        let mut row_count = 0;
+        while reader.read_byte_record(&mut record).map_err(csv_error)? {
+            self.check_row_limit(row_count + 1)?;
+            writer.write_record(indices.iter().map(|&idx| &record[idx])).map_err(csv_error)?;
+            row_count += 1;
+        }
+        writer.flush()?;
+
+        info!("Wrote {} rows to {}", row_count, output.display());
+        Ok(row_count)
+    }
+
+    /// Reads just the `name` column and returns it as a `Vec<T>`. Numeric columns convert to
+    /// any numeric `T` that holds every value; nulls are an error unless `T` is an `Option`.
+    pub fn load_column<T: ColumnElement>(&self, name: &str) -> Result<Vec<T>, LoaderError> {
+        let df = self.open_reader()?
+            .with_columns(Some(vec![name.to_string()]))
+            .finish()
+            .map_err(|e| match e {
+                PolarsError::ColumnNotFound(_) => LoaderError::MissingColumn(name.to_string()),
+                e => LoaderError::ProcessingError(e.to_string()),
+            })?;
+        self.check_row_limit(df.height())?;
+        let column = df.column(name).map_err(|_| LoaderError::MissingColumn(name.to_string()))?;
+
+        T::from_series(column)
+    }
+
+    /// Loads the file and profiles the resulting frame without reading it a second time.
+    pub fn load_data_with_profile(&self, options: &ProfileOptions) -> Result<(DataFrame, Profile), LoaderError> {
+        let df = self.load_data()?;
+        let profile = profile_frame(&df, options).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        Ok((df, profile))
+    }
+
+    pub fn load_data(&self) -> Result<DataFrame, LoaderError> {
+        self.load_data_with_report().map(|(df, _)| df)
+    }
+
+    /// Loads the file and splits its rows at random into `(train, test)`, with
+    /// `test_fraction` of them, rounded, in `test`. The same `seed` always picks the same
+    /// rows, and both frames keep the file's row order.
+    pub fn load_split(&self, test_fraction: f64, seed: u64) -> Result<(DataFrame, DataFrame), LoaderError> {
+        self.split_rows(test_fraction, seed, None)
+    }
+
+    /// Like `load_split`, but takes `test_fraction` of the rows for each value of `label`,
+    /// so both frames keep the file's label distribution.
+    pub fn load_split_stratified(
+        &self,
+        test_fraction: f64,
+        seed: u64,
+        label: &str,
+    ) -> Result<(DataFrame, DataFrame), LoaderError> {
+        self.split_rows(test_fraction, seed, Some(label))
+    }
+
+    fn split_rows(&self, test_fraction: f64, seed: u64, label: Option<&str>) -> Result<(DataFrame, DataFrame), LoaderError> {
+        if !(0.0..=1.0).contains(&test_fraction) {
+            return Err(LoaderError::ProcessingError(format!(
+                "test_fraction must be between 0 and 1, got {}",
+                test_fraction
+            )));
+        }
+        let df = self.load_data()?;
+
+        // Row indices grouped by label, groups in order of first appearance.
+        let groups: Vec<Vec<IdxSize>> = match label {
+            None => vec![(0..df.height() as IdxSize).collect()],
+            Some(label) => {
+                let labels = df.column(label)
+                    .map_err(|_| LoaderError::MissingColumn(label.to_string()))?
+                    .cast(&DataType::String)
+                    .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+                let labels = labels.str().map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+                let mut positions: HashMap<Option<&str>, usize> = HashMap::new();
+                let mut groups: Vec<Vec<IdxSize>> = Vec::new();
+                for (row, value) in labels.into_iter().enumerate() {
+                    let group = *positions.entry(value).or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+                    groups[group].push(row as IdxSize);
+                }
+                groups
+            },
+        };
+
+        let mut state = seed;
+        let (mut train, mut test) = (Vec::new(), Vec::new());
+        for mut rows in groups {
+            shuffle(&mut rows, &mut state);
+            let test_rows = (rows.len() as f64 * test_fraction).round() as usize;
+            test.extend_from_slice(&rows[..test_rows]);
+            train.extend_from_slice(&rows[test_rows..]);
+        }
+        train.sort_unstable();
+        test.sort_unstable();
+
+        let take = |rows: Vec<IdxSize>| {
+            df.take(&IdxCa::from_vec("", rows))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))
+        };
+        Ok((take(train)?, take(test)?))
+    }
+
+    /// Picks `n` rows uniformly at random, or every row if the file has fewer, in one
+    /// streaming pass that only holds the current candidates in memory. Whether a row is
+    /// picked depends on `seed` and its position alone, so a seed always gives the same
+    /// sample. Rows keep the file's order.
+    pub fn load_random_sample(&self, n: usize, seed: u64) -> Result<DataFrame, LoaderError> {
+        let _string_cache = StringCacheHolder::hold();
+        // Max-heap of the `n` smallest row keys seen so far. Every row with a key in it is
+        // kept, along with its `(key, row)` in `meta` in the same order as `kept`.
+        let mut smallest = BinaryHeap::with_capacity(n + 1);
+        let mut kept: Vec<DataFrame> = Vec::new();
+        let mut meta: Vec<(u64, u64)> = Vec::new();
+        let mut offset = 0u64;
+        self.for_each_batch(|batch| {
+            if kept.is_empty() {
+                kept.push(batch.clear());
+            }
+            let mut picked = Vec::new();
+            for idx in 0..batch.height() {
+                let row = offset + idx as u64;
+                let key = splitmix64(&mut (seed ^ row.wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+                if smallest.len() < n {
+                    smallest.push(key);
+                } else if smallest.peek().is_some_and(|&largest| key < largest) {
+                    smallest.pop();
+                    smallest.push(key);
+                } else {
+                    continue;
+                }
+                picked.push(idx as IdxSize);
+                meta.push((key, row));
+            }
+            offset += batch.height() as u64;
+
+            if !picked.is_empty() {
+                kept.push(batch.take(&IdxCa::from_vec("", picked)).map_err(|e| LoaderError::ProcessingError(e.to_string()))?);
+            }
+            // Rows pushed out of the heap are only dropped now and then, to keep memory
+            // around `n` rows without filtering on every batch.
+            if meta.len() > 2 * n.max(STREAM_BATCH_ROWS) {
+                Self::retain_sampled(&mut kept, &mut meta, smallest.peek().copied())?;
+            }
+            Ok(())
+        })?;
+
+        Self::retain_sampled(&mut kept, &mut meta, smallest.peek().copied())?;
+        let Some(sample) = kept.pop() else {
+            return Ok(self.dtypes.as_deref().map(DataFrame::from).unwrap_or_default());
+        };
+        let mut order: Vec<IdxSize> = (0..meta.len() as IdxSize).collect();
+        order.sort_unstable_by_key(|&idx| meta[idx as usize].1);
+        sample.take(&IdxCa::from_vec("", order)).map_err(|e| LoaderError::ProcessingError(e.to_string()))
+    }
+
+    // Merges the candidate frames into one, keeping only rows whose key is at most `largest`.
+    fn retain_sampled(kept: &mut Vec<DataFrame>, meta: &mut Vec<(u64, u64)>, largest: Option<u64>) -> Result<(), LoaderError> {
+        if kept.is_empty() {
+            return Ok(());
+        }
+        Self::align_optimized_types(kept)?;
+        let mut merged = kept[0].clone();
+        for chunk in &kept[1..] {
+            merged.vstack_mut(chunk).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+
+        let mask: BooleanChunked = meta.iter().map(|&(key, _)| largest.is_some_and(|largest| key <= largest)).collect();
+        let merged = merged.filter(&mask).map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        meta.retain(|&(key, _)| largest.is_some_and(|largest| key <= largest));
+        *kept = vec![merged];
+        Ok(())
+    }
+
+    /// Loads the data and hands back the Arrow arrays Polars holds it in, without copying,
+    /// for export over the Arrow C Data Interface. The schema describes every batch.
+    pub fn load_arrow(&self) -> Result<(ArrowSchema, Vec<RecordBatch>), LoaderError> {
+        let mut df = self.load_data()?;
+        df.align_chunks();
+        Ok((df.schema().to_arrow(), df.iter_chunks().collect()))
+    }
+
+    /// SHA-256 of the input as stored, compressed or not, in lowercase hex. The file is
+    /// streamed through the hasher, never parsed.
+    pub fn content_hash(&self) -> Result<String, LoaderError> {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        match &self.source {
+            CsvSource::File(path) => {
+                let mut file = std::fs::File::open(path)?;
+                let mut buffer = vec![0; 64 * 1024];
+                loop {
+                    let read = file.read(&mut buffer)?;
+                    if read == 0 {
+                        break;
+                    }
+                    context.update(&buffer[..read]);
+                }
+            },
+            CsvSource::Buffer(bytes) => context.update(bytes),
+        }
+        Ok(context.finish().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    /// Loads the data unless its `content_hash` equals `known_hash`, i.e. it was already
+    /// processed. Returns the frame with the new hash to store for next time.
+    pub fn load_if_changed(&self, known_hash: Option<&str>) -> Result<Option<(DataFrame, String)>, LoaderError> {
+        let hash = self.content_hash()?;
+        if known_hash == Some(hash.as_str()) {
+            info!("Input unchanged (sha256 {}), skipping load", hash);
+            return Ok(None);
+        }
+        Ok(Some((self.load_data()?, hash)))
+    }
+
+    /// Loads the rows that parse as the schema `load_data` would use, inferred from the first
+    /// rows or declared, and writes the others to `quarantine` as CSV instead of failing.
+    /// Quarantined rows keep their fields as read, followed by `_row`, the row's index
+    /// among the data rows, and `_reason`, naming the first field that didn't parse. The
+    /// input is read whole into memory.
+    pub fn load_with_quarantine(&self, quarantine: &Path) -> Result<(DataFrame, QuarantineCounts), LoaderError> {
+        let processing = |e: PolarsError| LoaderError::ProcessingError(e.to_string());
+        if self.is_empty_input()? {
+            return Ok((self.load_data()?, QuarantineCounts { loaded: 0, quarantined: 0 }));
+        }
+
+        let schema = self.open_reader()?
+            .infer_schema(Some(INFER_SCHEMA_ROWS))
+            .with_n_rows(Some(INFER_SCHEMA_ROWS))
+            // Polars may parse past `n_rows`, where a bad value would fail this pass.
+            .with_ignore_errors(true)
+            .finish()
+            .map_err(processing)?
+            .schema();
+        let as_text: Schema = schema.iter_names().map(|name| Field::new(name, DataType::String)).collect();
+        let text = self.open_reader()?
+            .with_dtypes(Some(Arc::new(as_text)))
+            .finish()
+            .map_err(processing)?;
+        self.check_row_limit(text.height())?;
+
+        let mut reasons: Vec<Option<String>> = vec![None; text.height()];
+        let mut parsed_columns = Vec::with_capacity(text.width());
+        for (column, dtype) in text.get_columns().iter().zip(schema.iter_dtypes()) {
+            let parsed = column.cast(dtype).map_err(processing)?;
+            let failed = &column.is_not_null() & &parsed.is_null();
+            let values = column.str().map_err(processing)?;
+            for (idx, failed) in failed.into_iter().enumerate() {
+                if failed == Some(true) && reasons[idx].is_none() {
+                    reasons[idx] = Some(format!(
+                        "{}: cannot parse {:?} as {}",
+                        column.name(),
+                        values.get(idx).unwrap_or_default(),
+                        dtype
+                    ));
+                }
+            }
+            parsed_columns.push(parsed);
+        }
+
+        // Defaults are filled before rejected rows are dropped, while rows still line up
+        // with the input.
+        let mut df = DataFrame::new(parsed_columns).map_err(processing)?;
+        if let Some(missing) = self.missing_fields_for_defaults()? {
+            Self::fill_column_defaults(&mut df, &self.config.column_defaults, &missing, 0, &Diagnostics::default())?;
+        }
+        let rejected = BooleanChunked::from_iter_values("rejected", reasons.iter().map(Option::is_some));
+        let mut df = df.filter(&!&rejected).map_err(processing)?;
+        let mut quarantined = text.filter(&rejected).map_err(processing)?;
+        let rows: Vec<u64> = (0..reasons.len() as u64).filter(|&idx| reasons[idx as usize].is_some()).collect();
+        quarantined
+            .with_column(Series::new("_row", rows))
+            .and_then(|df| df.with_column(Series::new("_reason", reasons.into_iter().flatten().collect::<Vec<_>>())))
+            .map_err(processing)?;
+        CsvWriter::new(std::fs::File::create(quarantine)?)
+            .finish(&mut quarantined)
+            .map_err(processing)?;
+
+        self.prepare_chunk(&mut df, 0, None, &Diagnostics::default())?;
+        let counts = QuarantineCounts { loaded: df.height(), quarantined: quarantined.height() };
+        if counts.quarantined > 0 {
+            warn!("Quarantined {} of {} rows to {}", counts.quarantined, text.height(), quarantine.display());
+        }
+        Ok((df, counts))
+    }
+
+    /// Loads the frame along with the byte range each row occupies in the input, as
+    /// half-open `(start, end)` offsets that leave out the line terminator. Offsets are
+    /// into the input as it is parsed: decompressed, and transcoded to UTF-8 when
+    /// `encoding` isn't UTF-8. The ranges come from a second, streaming pass over the input
+    /// after the frame is loaded.
+    pub fn load_with_offsets(&self) -> Result<(DataFrame, Vec<(u64, u64)>), LoaderError> {
+        let df = self.load_data()?;
+        let window = std::rc::Rc::new(std::cell::RefCell::new(ByteWindow::default()));
+        let mut reader = self.record_reader().from_reader(RecentBytes { inner: self.raw_reader()?, window: window.clone() });
+
+        let mut record = csv::ByteRecord::new();
+        let mut offsets = Vec::with_capacity(df.height());
+        let mut header = self.config.has_header;
+        while offsets.len() < df.height()
+            && reader.read_byte_record(&mut record).map_err(|e| LoaderError::ProcessingError(e.to_string()))?
+        {
+            let mut start = record.position().map_or(0, |position| position.byte());
+            let mut end = reader.position().byte();
+            let mut window = window.borrow_mut();
+            if std::mem::take(&mut header) {
+                window.discard_before(end);
+                continue;
+            }
+            // The reader stops at the `\r` of a `\r\n`, leaving the `\n` at the start of the next row.
+            while start < end && matches!(window.get(start), b'\r' | b'\n') {
+                start += 1;
+            }
+            while end > start && matches!(window.get(end - 1), b'\r' | b'\n') {
+                end -= 1;
+            }
+            offsets.push((start, end));
+            window.discard_before(end);
+        }
+
+        if offsets.len() != df.height() {
+            return Err(LoaderError::ProcessingError(format!(
+                "Found {} rows while tracking offsets, but loaded {}",
+                offsets.len(),
+                df.height()
+            )));
+        }
+        Ok((df, offsets))
+    }
+
+    pub fn load_data_with_diagnostics(&self) -> Result<(DataFrame, Diagnostics), LoaderError> {
+        self.load_data_with_report().map(|(df, report)| (df, report.diagnostics))
+    }
+
+    /// Loads the data along with chunk counts, memory before and after optimization, the
+    /// columns whose types were changed and how long it took.
+    pub fn load_data_with_report(&self) -> Result<(DataFrame, LoadReport), LoaderError> {
+        self.load_with_report(None)
+    }
+
+    /// Loads the data like `load_data`, passing each chunk through `f` once it has been
+    /// prepared and optimized and before chunks are concatenated. Chunks reach `f` in file
+    /// order; a file loaded whole is a single chunk. The first error from `f` stops the load.
+    pub fn load_data_with_transform(
+        &self,
+        f: impl Fn(DataFrame) -> Result<DataFrame, LoaderError>,
+    ) -> Result<DataFrame, LoaderError> {
+        self.load_with_report(Some(&f)).map(|(df, _)| df)
+    }
+
+    fn load_with_report(&self, transform: Option<ChunkTransform<'_>>) -> Result<(DataFrame, LoadReport), LoaderError> {
+        let started = Instant::now();
+        let mut result = self.read_with_report(transform);
+        if let Ok((_, report)) = &mut result {
+            report.duration = started.elapsed();
+        }
+        if let Some(sink) = &self.config.metrics {
+            let bytes = match &self.source {
+                CsvSource::File(path) => std::fs::metadata(path).ok().map(|m| m.len()),
+                CsvSource::Buffer(bytes) => Some(bytes.len() as u64),
+            };
+            sink.record_load("csv", &LoadMetrics::from_result(started, bytes, &result, |(df, _)| df.height()));
+        }
+        result
+    }
+
+    // Zero means the whole input is loaded in one go.
+    fn load_chunk_size(&self) -> Result<usize, LoaderError> {
+        match &self.source {
+            CsvSource::File(path) => {
+                let file_size = std::fs::metadata(path)?.len();
+                let estimated_size = if self.is_compressed() {
+                    (file_size as f64 * self.config.compressed_inflation) as u64
+                } else {
+                    file_size
+                };
+                self.calculate_chunk_size(estimated_size)
+            },
+            CsvSource::Buffer(_) => Ok(self.config.chunk_size.unwrap_or(0)),
+        }
+    }
+
+    /// Calls `f` with each batch in file order, prepared and optimized as `load_data` would,
+    /// and drops it before the next batch is read, so memory stays flat however large the
+    /// file is. Batches hold `chunk_size` rows, or `STREAM_BATCH_ROWS` when the file would
+    /// otherwise load whole. Each batch is optimized on its own, so a column's type can
+    /// differ between batches. Compressed, sanitized and in-memory input is still read
+    /// into memory once before being split.
+    pub fn for_each_batch<F>(&self, mut f: F) -> Result<(), LoaderError>
+    where
+        F: FnMut(&DataFrame) -> Result<(), LoaderError>,
+    {
+        let diagnostics = Diagnostics::default();
+        let batch_size = match self.load_chunk_size()? {
+            0 => STREAM_BATCH_ROWS,
+            size => size,
+        };
+
+        if self.is_empty_input()? {
+            return Ok(());
+        }
+
+        let missing = self.missing_fields_for_defaults()?;
+        let mut row_count = 0;
+        let mut visit = |mut batch: DataFrame| -> Result<(), LoaderError> {
+            let first_row = row_count;
+            row_count += batch.height();
+            self.check_row_limit(row_count)?;
+            self.prepare_chunk(&mut batch, first_row, missing.as_ref(), &diagnostics)?;
+            f(&batch)
+        };
+
+        let mut reader = self.open_reader()?.with_chunk_size(batch_size);
+        if self.reads_into_memory() || !self.has_data_rows()? {
+            let df = reader.finish().map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            for offset in (0..df.height()).step_by(batch_size) {
+                visit(df.slice(offset as i64, batch_size))?;
+            }
+        } else {
+            let mut batches = reader.batched_borrowed_read()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            while let Some(frames) = next_batches(&mut batches, 1)? {
+                for batch in frames {
+                    visit(batch)?;
+                }
+            }
+        }
+
+        info!("Streamed {} rows in batches of about {}", row_count, batch_size);
+        Ok(())
+    }
+
+    fn read_with_report(&self, transform: Option<ChunkTransform<'_>>) -> Result<(DataFrame, LoadReport), LoaderError> {
+        let diagnostics = Diagnostics::default();
+        if self.is_empty_input()? {
+            // Nothing to infer from, so only declared columns are known.
+            let df = self.dtypes.as_deref().map(DataFrame::from).unwrap_or_default();
+            return Ok(Self::report(df, 0, 0, diagnostics));
+        }
+        let chunk_size = self.load_chunk_size()?;
+        let missing = self.missing_fields_for_defaults()?;
+
+        info!("Loading CSV with chunk size: {}", if chunk_size > 0 { chunk_size.to_string() } else { "Full file".to_string() });
+
+        if chunk_size == 0 {
+            let mut df = self.open_reader()?
+                .finish()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            self.check_row_limit(df.height())?;
+
+            let original_bytes = df.estimated_size();
+            self.prepare_chunk(&mut df, 0, missing.as_ref(), &diagnostics)?;
+            if let Some(transform) = transform {
+                df = transform(df)?;
+            }
+            info!("Successfully loaded data with shape: {:?}", df.shape());
+            Ok(Self::report(df, 1, original_bytes, diagnostics))
+        } else {
+            // Categorical chunks can only be concatenated if they share one string cache.
+            let _string_cache = StringCacheHolder::hold();
+            let mut chunks = Vec::new();
+            let mut original_bytes = 0;
+            let mut rows_read = 0;
+            // Returns the batch's size before optimization. The row limit counts rows as read,
+            // before any transform drops some.
+            let mut prepare = |chunks: &mut Vec<DataFrame>, batch: Vec<DataFrame>| -> Result<usize, LoaderError> {
+                let size = batch.iter().map(DataFrame::estimated_size).sum();
+                let first_rows: Vec<usize> = batch.iter()
+                    .scan(rows_read, |next, chunk| {
+                        let first = *next;
+                        *next += chunk.height();
+                        Some(first)
+                    })
+                    .collect();
+                let prepared: Result<Vec<DataFrame>, LoaderError> = self.thread_pool()?.install(|| {
+                    batch
+                        .into_par_iter()
+                        .zip(first_rows)
+                        .map(|(mut chunk, first_row)| {
+                            self.prepare_chunk(&mut chunk, first_row, missing.as_ref(), &diagnostics)?;
+                            Ok(chunk)
+                        })
+                        .collect()
+                });
+                let prepared = prepared?;
+                rows_read += prepared.iter().map(DataFrame::height).sum::<usize>();
+                self.check_row_limit(rows_read)?;
+                match transform {
+                    Some(transform) => {
+                        for chunk in prepared {
+                            chunks.push(transform(chunk)?);
+                        }
+                    },
+                    None => chunks.extend(prepared),
+                }
+                Ok(size)
+            };
+
+            let mut reader = self.open_reader()?.with_chunk_size(chunk_size);
+            if self.config.schema_drift == SchemaDriftPolicy::Reconcile {
+                if let Some((chunk, expected, found)) = self.find_extra_fields(chunk_size)? {
+                    warn!("Chunk {} has columns {:?}, expected {:?}; dropping the extra fields", chunk, found, expected);
+                    reader = reader.truncate_ragged_lines(true);
+                }
+            }
+            let read_error = |e: PolarsError| self.explain_read_error(LoaderError::ProcessingError(e.to_string()), chunk_size);
+            if self.reads_into_memory() || !self.has_data_rows()? {
+                // Batched reads need a file-backed reader with at least one row, so in-memory
+                // input is read whole and sliced instead.
+                let df = reader.finish().map_err(read_error)?;
+                original_bytes += prepare(
+                    &mut chunks,
+                    (0..df.height().max(1))
+                        .step_by(chunk_size)
+                        .map(|offset| df.slice(offset as i64, chunk_size))
+                        .collect(),
+                )?;
+            } else {
+                // Each call hands back the next `worker_count` batches, read once and in file order.
+                let mut batches = reader.batched_borrowed_read().map_err(read_error)?;
+                while let Some(batch) = next_batches(&mut batches, self.config.worker_count())
+                    .map_err(|e| self.explain_read_error(e, chunk_size))?
+                {
+                    original_bytes += prepare(&mut chunks, batch)?;
+                }
+            }
+
+            info!("Read {} chunks of about {} rows", chunks.len(), chunk_size);
+            Self::check_chunk_columns(&mut chunks, self.config.schema_drift)?;
+            Self::align_optimized_types(&mut chunks)?;
+            if self.config.auto_widen {
+                Self::widen_chunk_types(&mut chunks)?;
+            }
+
+            let chunk_count = chunks.len();
+            let df = concat(chunks.as_slice(), true)
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+            info!("Successfully loaded data with shape: {:?}", df.shape());
+            Ok(Self::report(df, chunk_count, original_bytes, diagnostics))
+        }
+    }
+
+    // Conversions are recorded per chunk, so their target is replaced with the column's
+    // final type once chunks have been aligned and concatenated.
+    fn report(df: DataFrame, chunks: usize, original_bytes: usize, diagnostics: Diagnostics) -> (DataFrame, LoadReport) {
+        let conversions = std::mem::take(&mut *diagnostics.conversions.lock().unwrap_or_else(PoisonError::into_inner))
+            .into_iter()
+            .filter_map(|mut conversion| {
+                conversion.to = df.column(&conversion.column).ok()?.dtype().clone();
+                (conversion.to != conversion.from).then_some(conversion)
+            })
+            .collect();
+
+        let report = LoadReport {
+            rows: df.height(),
+            chunks,
+            original_bytes,
+            optimized_bytes: df.estimated_size(),
+            conversions,
+            duration: Duration::ZERO,
+            diagnostics,
+        };
+        (df, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use std::io::Write;
+
+    #[test]
+    fn test_csv_loader() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.load_data()?;
+
+        assert_eq!(df.shape(), (3, 3));
+        assert_eq!(df.column("id")?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_data_with_transform() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for id in 1..=7 {
+            writeln!(file, "{},{}", id, id * 10)?;
+        }
+        let config = LoaderConfig { chunk_size: Some(3), ..Default::default() };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let df = loader.load_data_with_transform(|chunk| {
+            chunk.lazy()
+                .filter((col("id") % lit(2)).eq(lit(0)))
+                .collect()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))
+        })?;
+
+        let ids: Vec<i64> = df.column("id")?.cast(&DataType::Int64)?.i64()?.into_no_null_iter().collect();
+        assert_eq!(ids, vec![2, 4, 6]);
+        assert_eq!(df.column("value")?.cast(&DataType::Int64)?.i64()?.get(2), Some(60));
+
+        let failed = loader.load_data_with_transform(|_| Err(LoaderError::ProcessingError("rejected".to_string())));
+        assert!(matches!(failed, Err(LoaderError::ProcessingError(message)) if message == "rejected"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_random_sample() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,group")?;
+        for id in 0..1000 {
+            writeln!(file, "{},{}", id, ["a", "b", "c"][id % 3])?;
+        }
+        let config = LoaderConfig { chunk_size: Some(64), ..Default::default() };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+        let ids = |df: &DataFrame| -> Result<Vec<i64>, Box<dyn Error>> {
+            Ok(df.column("id")?.cast(&DataType::Int64)?.i64()?.into_no_null_iter().collect())
+        };
+
+        let sample = loader.load_random_sample(10, 7)?;
+        let picked = ids(&sample)?;
+
+        assert_eq!(sample.shape(), (10, 2));
+        assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ids(&loader.load_random_sample(10, 7)?)?, picked);
+        assert_eq!(ids(&CSVLoader::new(file.path(), None)?.load_random_sample(10, 7)?)?, picked);
+        assert_ne!(ids(&loader.load_random_sample(10, 8)?)?, picked);
+        assert_eq!(ids(&loader.load_random_sample(5000, 7)?)?, (0..1000).collect::<Vec<_>>());
+        assert_eq!(loader.load_random_sample(0, 7)?.shape(), (0, 2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_data_with_report() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "seq,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,A")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let (df, report) = CSVLoader::new(file.path(), None)?.load_data_with_report()?;
+
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.chunks, 1);
+        assert_eq!(report.categorical_columns(), ["category"]);
+        assert!(report.conversions.iter().any(|c| c.column == "seq" && c.from == DataType::Int64 && c.to == DataType::UInt8));
+        assert!(report.optimized_bytes < report.original_bytes);
+        assert_eq!(report.optimized_bytes, df.estimated_size());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_columns_are_not_downcast() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "user_id,count,Order_ID,ref_id")?;
+        writeln!(file, "1,3,a1,x")?;
+        writeln!(file, "2,4,a2,x")?;
+        writeln!(file, "3,5,a3,x")?;
+
+        let df = CSVLoader::new(file.path(), None)?.load_data()?;
+
+        assert_eq!(df.column("user_id")?.dtype(), &DataType::Int64);
+        assert_eq!(df.column("count")?.dtype(), &DataType::UInt8);
+        assert_eq!(df.column("Order_ID")?.dtype(), &DataType::String);
+        assert_eq!(df.column("ref_id")?.dtype(), &DataType::String);
+
+        let config = LoaderConfig { id_column_pattern: None, ..Default::default() };
+        let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+        assert_eq!(df.column("user_id")?.dtype(), &DataType::UInt8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delimiter_and_null_values() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id|name|score")?;
+        writeln!(file, "1|'a|b'|NA")?;
+        writeln!(file, "2|NA|7.5")?;
+        writeln!(file, "3|c|NA")?;
+
+        let config = LoaderConfig {
+            delimiter: b'|',
+            quote_char: Some(b'\''),
+            null_values: Some(vec!["NA".to_string()]),
+            ..Default::default()
+        };
+        let df = CSVLoader::new(file.path(), Some(config.clone()))?.load_data()?;
+
+        assert_eq!(df.shape(), (3, 3));
+        assert_eq!(df.column("score")?.null_count(), 2);
+        assert_eq!(df.column("name")?.null_count(), 1);
+        assert_eq!(df.column("name")?.cast(&DataType::String)?.str()?.get(0), Some("a|b"));
+
+        let mut headless = NamedTempFile::new()?;
+        writeln!(headless, "1|x")?;
+        writeln!(headless, "2|NA")?;
+        let config = LoaderConfig { has_header: false, chunk_size: Some(1), ..config };
+        let df = CSVLoader::new(headless.path(), Some(config))?.load_data()?;
+
+        assert_eq!(df.shape(), (2, 2));
+        assert_eq!(df.column("column_2")?.null_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_hash() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        writeln!(file, "1,10.5")?;
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let hash = loader.content_hash()?;
+        assert_eq!(hash.len(), 64);
+        assert_eq!(loader.content_hash()?, hash);
+        assert!(loader.load_if_changed(Some(&hash))?.is_none());
+
+        writeln!(file, "2,20.7")?;
+        let changed = loader.content_hash()?;
+        assert_ne!(changed, hash);
+        let (df, stored) = loader.load_if_changed(Some(&hash))?.expect("changed file is reloaded");
+        assert_eq!((df.height(), stored), (2, changed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimization_settings() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,price,category")?;
+        for i in 0..10 {
+            writeln!(file, "{},{}.01,{}", i, i, if i % 2 == 0 { "A" } else { "B" })?;
+        }
+
+        let config = LoaderConfig {
+            downcast_floats: false,
+            downcast_ints: false,
+            categorical_threshold: 0.1,
+            ..Default::default()
+        };
+        let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+
+        assert_eq!(df.column("price")?.dtype(), &DataType::Float64);
+        assert_eq!(df.column("id")?.dtype(), &DataType::Int64);
+        assert_eq!(df.column("category")?.dtype(), &DataType::String);
+
+        let df = CSVLoader::new(file.path(), None)?.load_data()?;
+
+        assert_eq!(df.column("price")?.dtype(), &DataType::Float32);
+        assert!(matches!(df.column("category")?.dtype(), DataType::Categorical(..)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_arrow() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,score,label")?;
+        for i in 0..50 {
+            writeln!(file, "{},{}.5,{}", i, i, if i % 3 == 0 { "x" } else { "y" })?;
+        }
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.load_data()?;
+        let (schema, batches) = loader.load_arrow()?;
+
+        assert_eq!(schema, df.schema().to_arrow());
+        assert_eq!(batches.iter().map(|batch| batch.len()).sum::<usize>(), df.height());
+        for batch in &batches {
+            let dtypes: Vec<_> = batch.arrays().iter().map(|array| array.data_type()).collect();
+            assert_eq!(dtypes, schema.fields.iter().map(|field| &field.data_type).collect::<Vec<_>>());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_and_header_only_files() -> Result<(), Box<dyn Error>> {
+        let empty = NamedTempFile::new()?;
+        let df = CSVLoader::new(empty.path(), None)?.load_data()?;
+        assert_eq!(df.shape(), (0, 0));
+
+        let mut header_only = NamedTempFile::new()?;
+        writeln!(header_only, "id,name,score")?;
+        let df = CSVLoader::new(header_only.path(), None)?.load_data()?;
+        assert_eq!(df.shape(), (0, 3));
+        assert_eq!(df.get_column_names(), &["id", "name", "score"]);
+
+        // The chunked path reaches the same result.
+        let config = LoaderConfig { chunk_size: Some(10), ..Default::default() };
+        let df = CSVLoader::new(header_only.path(), Some(config.clone()))?.load_data()?;
+        assert_eq!(df.shape(), (0, 3));
+        let df = CSVLoader::new(empty.path(), Some(config))?.load_data()?;
+        assert_eq!(df.shape(), (0, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_with_quarantine() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,amount,label")?;
+        for i in 0..200 {
+            if i == 150 {
+                writeln!(file, "{},n/a,bad", i)?;
+            } else {
+                writeln!(file, "{},{}.25,ok", i, i)?;
+            }
+        }
+        let dir = tempfile::tempdir()?;
+        let quarantine = dir.path().join("quarantine.csv");
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        // The bad row is past the inference window, so a plain load fails on it.
+        assert!(loader.load_data().is_err());
+
+        let (df, counts) = loader.load_with_quarantine(&quarantine)?;
+
+        assert_eq!(counts, QuarantineCounts { loaded: 199, quarantined: 1 });
+        assert_eq!(df.height(), 199);
+        assert_eq!(df.column("amount")?.null_count(), 0);
+        let rejected = CsvReader::from_path(&quarantine)?.finish()?;
+        assert_eq!(rejected.get_column_names(), &["id", "amount", "label", "_row", "_reason"]);
+        assert_eq!(rejected.column("_row")?.get(0)?, AnyValue::Int64(150));
+        assert_eq!(rejected.column("_reason")?.str()?.get(0), Some("amount: cannot parse \"n/a\" as f64"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trim_fields() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,name")?;
+        writeln!(file, "1,  alpha ")?;
+        writeln!(file, "2,beta")?;
+
+        let config = LoaderConfig {
+            trim_fields: true,
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let df = loader.load_data()?;
+        let names = df.column("name")?.cast(&DataType::String)?;
+
+        assert_eq!(names.str()?.get(0), Some("alpha"));
+        assert_eq!(names.str()?.get(1), Some("beta"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_streaming() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "category,value")?;
+        for i in 0..10_000 {
+            writeln!(file, "{},{}", if i % 2 == 0 { "A" } else { "B" }, 1)?;
+        }
+
+        // The eager load reads ten chunks; streaming over the same file must agree with it.
+        let config = LoaderConfig {
+            chunk_size: Some(1_000),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let df = loader
+            .aggregate_streaming(&["category"], &[col("value").sum()])?
+            .sort(["category"], false, false)?;
+        let chunked = loader.load_data()?
+            .lazy()
+            .group_by([col("category")])
+            .agg([col("value").cast(DataType::Int64).sum()])
+            .sort("category", Default::default())
+            .collect()?;
+
+        assert_eq!(df.shape(), (2, 2));
+        assert_eq!(df.column("value")?.i64()?.get(0), Some(5_000));
+        assert_eq!(df.column("value")?.i64()?.get(1), Some(5_000));
+        assert!(df.column("value")?.equals(chunked.column("value")?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_defaults() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.25")?;
+        // Present but empty, so it stays null.
+        writeln!(file, "3,,")?;
+        writeln!(file, "4")?;
+
+        let config = LoaderConfig {
+            column_defaults: HashMap::from([
+                ("category".to_string(), AnyValue::StringOwned("unknown".into())),
+                ("value".to_string(), AnyValue::Float64(0.0)),
+            ]),
+            ..Default::default()
+        };
+        let full = CSVLoader::new(file.path(), Some(config.clone()))?.load_data()?;
+        let chunked = CSVLoader::new(file.path(), Some(LoaderConfig { chunk_size: Some(1), ..config }))?.load_data()?;
+
+        for df in [full, chunked] {
+            let categories = df.column("category")?.cast(&DataType::String)?;
+            let categories: Vec<_> = categories.str()?.into_iter().collect();
+            let values: Vec<_> = df.column("value")?.cast(&DataType::Float64)?.f64()?.into_iter().collect();
+
+            assert_eq!(categories, [Some("A"), Some("unknown"), None, Some("unknown")]);
+            assert_eq!(values, [Some(10.5), Some(20.25), None, Some(0.0)]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_row() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let mut seen = Vec::new();
+        let row_count = loader.for_each_row(|row| {
+            assert_eq!(row.len(), 3);
+            seen.push(row[0].to_string());
+        })?;
+
+        assert_eq!(row_count, 3);
+        assert_eq!(seen, vec!["1", "2", "3"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_polars_threads() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let config = LoaderConfig {
+            num_workers: 4,
+            polars_threads: Some(1),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let df = loader.load_data()?;
+
+        assert_eq!(loader.reader_threads(), 1);
+        assert!(df.equals_missing(&CSVLoader::new(file.path(), None)?.load_data()?));
+        assert_eq!(df.shape(), (3, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics_record_skipped_float_downcast() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        writeln!(file, "1,1.5")?;
+        writeln!(file, "2,1e300")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let (df, diagnostics) = loader.load_data_with_diagnostics()?;
+
+        assert_eq!(df.column("value")?.dtype(), &DataType::Float64);
+        let entries = diagnostics.into_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].code, DiagnosticCode::FloatDowncastSkipped);
+        assert_eq!(entries[0].column.as_deref(), Some("value"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_columns() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,tags")?;
+        writeln!(file, "1,\"[1,2,3]\"")?;
+        writeln!(file, "2,\"[4]\"")?;
+        writeln!(file, "3,\"[5,\"")?;
+
+        let config = LoaderConfig {
+            json_columns: vec!["tags".to_string()],
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let (df, diagnostics) = loader.load_data_with_diagnostics()?;
+        let tags = df.column("tags")?;
+
+        assert_eq!(tags.dtype(), &DataType::List(Box::new(DataType::Int64)));
+        assert_eq!(tags.list()?.get_as_series(0).map(|s| s.len()), Some(3));
+        assert_eq!(tags.null_count(), 1);
+        assert!(diagnostics.has(DiagnosticCode::MalformedJson));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_schema_json() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+        let schema_file = NamedTempFile::new()?;
+
+        loader.export_schema_json(schema_file.path())?;
+
+        let document: SchemaDocument = serde_json::from_str(&std::fs::read_to_string(schema_file.path())?)?;
+        assert!(document.columns.iter().any(|c| c.name == "value" && c.nullable));
+        assert!(document.columns.iter().any(|c| c.name == "id" && !c.nullable));
+
+        let schema = read_schema_json(schema_file.path())?;
+        assert_eq!(schema, loader.load_data()?.schema());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_schema() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,,B")?;
+        let schema_file = NamedTempFile::new()?;
+        CSVLoader::new(file.path(), None)?.export_schema_json(schema_file.path())?;
+
+        CSVLoader::new(file.path(), None)?.validate_schema(schema_file.path())?;
+
+        // A later delivery breaks the contract in every way at once.
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,region")?;
+        writeln!(file, ",high,EU")?;
+        writeln!(file, "4,low,US")?;
+        let err = CSVLoader::new(file.path(), None)?.validate_schema(schema_file.path()).unwrap_err();
+
+        let LoaderError::SchemaViolation(violations) = err else {
+            panic!("expected a schema violation, got {}", err);
+        };
+        assert_eq!(violations, [
+            "column 'id' has 1 nulls but isn't nullable",
+            "column 'value' is String, expected Float32",
+            "column 'category' is missing",
+            "column 'region' isn't in the schema",
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecar_schema() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("accounts.csv");
+        std::fs::write(&path, "id,balance,tier\n001,10.5,A\n002,20.5,A\n003,30.5,A\n")?;
+
+        let inferred = CSVLoader::new(&path, None)?.with_sidecar_schema()?.load_data()?;
+        assert!(inferred.column("id")?.dtype().is_numeric());
+
+        std::fs::write(
+            dir.path().join("accounts.schema.json"),
+            r#"{"columns": [
+                {"name": "id", "dtype": "String", "nullable": false},
+                {"name": "balance", "dtype": "Float64", "nullable": false}
+            ]}"#,
+        )?;
+        let df = CSVLoader::new(&path, None)?.with_sidecar_schema()?.load_data()?;
+
+        assert_eq!(df.column("id")?.str()?.get(0), Some("001"));
+        assert_eq!(df.column("balance")?.dtype(), &DataType::Float64);
+        assert!(matches!(df.column("tier")?.dtype(), DataType::Categorical(..)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget_forces_small_chunks() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        for i in 0..100 {
+            writeln!(file, "{},{}.5,{}", i, i, if i % 2 == 0 { "A" } else { "B" })?;
+        }
+
+        let file_size = std::fs::metadata(file.path())?.len();
+        let config = LoaderConfig {
+            memory_budget_bytes: Some(file_size * 2),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let chunk_size = loader.calculate_chunk_size(file_size)?;
+        assert!(chunk_size > 0 && chunk_size < 100, "chunk size was {}", chunk_size);
+
+        let df = loader.load_data()?;
+        assert_eq!(df.shape(), (100, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget_below_result_size_fails() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..100 {
+            writeln!(file, "{},{}.5", i, i)?;
+        }
+
+        let config = LoaderConfig { memory_budget_bytes: Some(64), ..Default::default() };
+        let err = CSVLoader::new(file.path(), Some(config))?.load_data().unwrap_err();
+
+        assert!(matches!(err, LoaderError::MemoryBudgetExceeded { budget: 64, .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_size_override() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        // The override wins over a budget the result would never fit in.
+        let config = LoaderConfig {
+            chunk_size: Some(2),
+            memory_budget_bytes: Some(1),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+        let file_size = std::fs::metadata(file.path())?.len();
+
+        let chunk_size = loader.calculate_chunk_size(file_size)?;
+        assert_eq!(chunk_size, 2);
+        assert_eq!(loader.load_data()?.shape(), (3, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_larger_than_file_loads_every_row() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..2_000 {
+            writeln!(file, "{},{}", i, i % 10)?;
+        }
+
+        let config = LoaderConfig { chunk_size: Some(100_000), ..Default::default() };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        assert_eq!(loader.load_data()?.shape(), (2_000, 2));
+        let mut rows = 0;
+        loader.for_each_batch(|batch| {
+            rows += batch.height();
+            Ok(())
+        })?;
+        assert_eq!(rows, 2_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_overrides_pin_types() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,zip,value")?;
+        for (i, zip) in ["00123", "04567", "00123", "00123", "04567"].iter().enumerate() {
+            writeln!(file, "{},{},{}", i, zip, i * 10)?;
+        }
+
+        let config = LoaderConfig {
+            schema_overrides: Some(HashMap::from([("zip".to_string(), DataType::String)])),
+            columns: Some(vec!["id".to_string(), "zip".to_string()]),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        for df in [loader.load_data()?, loader.scan()?.collect()?] {
+            assert_eq!(df.get_column_names(), ["id", "zip"]);
+            assert_eq!(df.column("zip")?.dtype(), &DataType::String);
+            assert_eq!(df.column("zip")?.str()?.get(1), Some("04567"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_with_offsets() -> Result<(), Box<dyn Error>> {
+        let contents = "id,value,note\n1,10.5,plain\r\n2,20.7,\"two\nlines\"\n3,30.2,last";
+        let mut file = NamedTempFile::new()?;
+        file.write_all(contents.as_bytes())?;
+
+        let (df, offsets) = CSVLoader::new(file.path(), None)?.load_with_offsets()?;
+
+        assert_eq!(df.height(), 3);
+        let rows: Vec<&str> = offsets.iter().map(|&(start, end)| &contents[start as usize..end as usize]).collect();
+        assert_eq!(rows, ["1,10.5,plain", "2,20.7,\"two\nlines\"", "3,30.2,last"]);
+
+        // Enough rows to span many reads, with offsets into the transcoded text.
+        let lines: Vec<String> = (0..5_000).map(|i| format!("{},{}.5,caf\u{e9} {}", i, i, i)).collect();
+        let mut file = NamedTempFile::new()?;
+        file.write_all(b"id,value,note\r\n")?;
+        for line in &lines {
+            file.write_all(&encoding_rs::WINDOWS_1252.encode(line).0)?;
+            file.write_all(b"\r\n")?;
+        }
+        let config = LoaderConfig { encoding: Encoding::Latin1, ..Default::default() };
+        let (df, offsets) = CSVLoader::new(file.path(), Some(config))?.load_with_offsets()?;
+        let text = format!("id,value,note\r\n{}\r\n", lines.join("\r\n"));
+
+        assert_eq!(df.height(), lines.len());
+        let rows: Vec<&str> = offsets.iter().map(|&(start, end)| &text[start as usize..end as usize]).collect();
+        assert_eq!(rows, lines);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_batch_streams_every_row() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..2_000 {
+            writeln!(file, "{},{}", i, i % 10)?;
+        }
+
+        let config = LoaderConfig { chunk_size: Some(100), ..Default::default() };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let mut batches = 0;
+        let mut total = 0;
+        loader.for_each_batch(|batch| {
+            batches += 1;
+            let values = batch.column("value").map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            total += values.sum::<i64>().unwrap_or(0);
+            Ok(())
+        })?;
+
+        assert!(batches > 1);
+        assert_eq!(total, (0..2_000).map(|i| i % 10).sum::<i64>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_load_reads_each_row_once() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,category")?;
+        for i in 0..5_000 {
+            writeln!(file, "{},{}", i, if i % 3 == 0 { "A" } else { "B" })?;
+        }
+
+        for num_workers in [3, 1] {
+            let config = LoaderConfig {
+                num_workers,
+                chunk_size: Some(100),
+                ..Default::default()
+            };
+            let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+
+            assert_eq!(df.height(), 5_000);
+            assert!(matches!(df.column("category")?.dtype(), DataType::Categorical(..)));
+            let ids = df.column("id")?.cast(&DataType::Int64)?;
+            assert_eq!(ids.n_unique()?, 5_000);
+            assert!(ids.i64()?.into_no_null_iter().eq(0..5_000));
+        }
+
+        // In-memory input is sliced into chunks of the same size.
+        let config = LoaderConfig { chunk_size: Some(100), ..Default::default() };
+        let df = CSVLoader::from_reader(std::fs::File::open(file.path())?, Some(config))?.load_data()?;
+        assert_eq!(df.column("id")?.cast(&DataType::Int64)?.n_unique()?, 5_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_pushes_down_filter_and_select() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        for i in 0..10 {
+            writeln!(file, "{},{}.5,{}", i, i, if i % 2 == 0 { "A" } else { "B" })?;
+        }
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.scan()?
+            .filter(col("id").gt_eq(lit(6)))
+            .select([col("id"), col("category")])
+            .collect()?;
+        assert_eq!(df.shape(), (4, 2));
+        assert_eq!(df.column("category")?.dtype(), &DataType::String);
+
+        let optimized = loader.scan_builder()
+            .with_optimizations(true)
+            .finish()?
+            .collect()?;
+        assert!(matches!(optimized.column("category")?.dtype(), DataType::Categorical(..)));
+        assert_eq!(optimized.column("value")?.dtype(), &DataType::Float32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_melted() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,B")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.load_melted(&["id"], &["value", "category"])?;
+        assert_eq!(df.shape(), (6, 3));
+        assert_eq!(df.get_column_names(), ["id", "variable", "value"]);
+
+        let missing = loader.load_melted(&["id"], &["missing"]);
+        assert!(matches!(missing, Err(LoaderError::MissingColumn(name)) if name == "missing"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_pivoted() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,metric,value")?;
+        writeln!(file, "1,height,10")?;
+        writeln!(file, "1,width,20")?;
+        writeln!(file, "2,height,30")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.load_pivoted(&["id"], &["metric"], &["value"])?;
+        assert_eq!(df.shape(), (2, 3));
+        assert_eq!(df.column("width")?.null_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_column_drift_is_reported() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..5 {
+            writeln!(file, "{},{}.5", i, i)?;
+        }
+        // A stray line in the third chunk.
+        writeln!(file, "5,5.5,stray")?;
+
+        let config = LoaderConfig { chunk_size: Some(2), ..Default::default() };
+        let err = CSVLoader::new(file.path(), Some(config))?.load_data().unwrap_err();
+
+        assert!(matches!(err, LoaderError::SchemaDrift { chunk: 2, .. }));
+        assert_eq!(
+            err.to_string(),
+            r#"Chunk 2 has columns ["id", "value", "column_3"], expected ["id", "value"]"#
+        );
+
+        let config = LoaderConfig {
+            chunk_size: Some(2),
+            schema_drift: SchemaDriftPolicy::Reconcile,
+            ..Default::default()
+        };
+        let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+
+        assert_eq!(df.get_column_names(), ["id", "value"]);
+        assert_eq!(df.height(), 6);
+        assert_eq!(df.column("value")?.cast(&DataType::Float64)?.f64()?.get(5), Some(5.5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_column_drift_is_reconciled() -> Result<(), Box<dyn Error>> {
+        let mut chunks = vec![
+            df!("id" => [1i64], "value" => [10.5])?,
+            df!("id" => [3i64], "value" => [30.2], "stray" => ["x"])?,
+        ];
+
+        CSVLoader::check_chunk_columns(&mut chunks, SchemaDriftPolicy::Reconcile)?;
+
+        assert_eq!(chunks[0].get_column_names(), ["id", "value", "stray"]);
+        assert_eq!(chunks[0].schema(), chunks[1].schema());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_types_are_widened() -> Result<(), Box<dyn Error>> {
+        // Each chunk infers its own JSON type, and the later one has different fields.
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,payload")?;
+        for i in 0..4 {
+            writeln!(file, r#"{},"{{""a"": {}}}""#, i, i)?;
+        }
+        for i in 4..8 {
+            writeln!(file, r#"{},"{{""b"": ""x{}""}}""#, i, i)?;
+        }
+
+        let config = |auto_widen| LoaderConfig {
+            chunk_size: Some(2),
+            json_columns: vec!["payload".to_string()],
+            auto_widen,
+            ..Default::default()
+        };
+        assert!(CSVLoader::new(file.path(), Some(config(false)))?.load_data().is_err());
+
+        let df = CSVLoader::new(file.path(), Some(config(true)))?.load_data()?;
+
+        assert_eq!(
+            df.column("payload")?.dtype(),
+            &DataType::Struct(vec![Field::new("a", DataType::Int64), Field::new("b", DataType::String)])
+        );
+        let fields = df.column("payload")?.struct_()?.fields().to_vec();
+        assert_eq!(fields[0].i64()?.get(3), Some(3));
+        assert_eq!(fields[0].null_count(), 4);
+        assert_eq!(fields[1].str()?.get(4), Some("x4"));
+        assert_eq!(fields[1].null_count(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_control_chars() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(b"id,quote\n")?;
+        file.write_all(b"1,\x93hello\x94 \x96 world\x07\n")?;
+        file.write_all("2,caf\u{e9}\n".as_bytes())?;
+
+        let config = LoaderConfig {
+            sanitize_control_chars: true,
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+
+        let df = loader.load_data()?;
+        let quotes = df.column("quote")?.cast(&DataType::String)?;
+
+        assert_eq!(quotes.str()?.get(0), Some("\u{201c}hello\u{201d} \u{2013} world"));
+        assert_eq!(quotes.str()?.get(1), Some("caf\u{e9}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_latin1_input_is_transcoded() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(b"id,name\n1,caf\xe9\n2,na\xefve\n")?;
+
+        for encoding in [Encoding::Latin1, Encoding::Auto] {
+            let config = LoaderConfig { encoding, categorical_threshold: 0.0, ..Default::default() };
+            let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+
+            let names = df.column("name")?.str()?.into_no_null_iter().collect::<Vec<_>>();
+            assert_eq!(names, vec!["caf\u{e9}", "na\u{ef}ve"]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_utf16_input_is_detected_from_bom() -> Result<(), Box<dyn Error>> {
+        let text = "id,name\n1,caf\u{e9}\n";
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+        let config = LoaderConfig { encoding: Encoding::Auto, categorical_threshold: 0.0, ..Default::default() };
+        let df = CSVLoader::from_reader(&bytes[..], Some(config))?.load_data()?;
+
+        assert_eq!(df.get_column_names(), vec!["id", "name"]);
+        assert_eq!(df.column("name")?.str()?.get(0), Some("caf\u{e9}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_utf16_is_a_processing_error() -> Result<(), Box<dyn Error>> {
+        // A lone high surrogate after the header row.
+        let mut bytes: Vec<u8> = "id\n1\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        bytes.extend([0x00, 0xd8, b'\n', 0x00]);
+
+        let config = LoaderConfig { encoding: Encoding::Utf16, ..Default::default() };
+        let err = CSVLoader::from_reader(&bytes[..], Some(config))?.load_data().unwrap_err();
+
+        let LoaderError::ProcessingError(message) = err else {
+            panic!("expected a processing error, got {:?}", err);
+        };
+        assert!(message.contains("UTF-16LE") && message.contains("byte 10"), "{}", message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_preserve_nullable_ints() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,ratio")?;
+        writeln!(file, "1.0,0.5")?;
+        writeln!(file, ",1.5")?;
+        writeln!(file, "300.0,")?;
+
+        let config = LoaderConfig {
+            preserve_nullable_ints: true,
+            ..Default::default()
+        };
+        let df = CSVLoader::new(file.path(), Some(config))?.load_data()?;
+
+        let id = df.column("id")?;
+        assert!(id.dtype().is_integer(), "id should be an integer column, got {}", id.dtype());
+        assert_eq!(id.null_count(), 1);
+        assert_eq!(id.cast(&DataType::Int64)?.i64()?.get(2), Some(300));
+        assert!(df.column("ratio")?.dtype().is_float());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_currency_columns() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,price")?;
+        writeln!(file, "1,\"$1,234.56\"")?;
+        writeln!(file, "2,€10")?;
+        writeln!(file, "3,($5.00)")?;
+        writeln!(file, "4,n/a")?;
+
+        let config = LoaderConfig {
+            currency_columns: vec!["price".to_string()],
+            capture_currency_symbol: true,
+            ..Default::default()
+        };
+        let (df, diagnostics) = CSVLoader::new(file.path(), Some(config))?.load_data_with_diagnostics()?;
+
+        // optimize_chunk narrows the parsed Float64 to Float32.
+        let price = df.column("price")?.cast(&DataType::Float64)?;
+        let price = price.f64()?;
+        assert!((price.get(0).unwrap() - 1234.56).abs() < 1e-3);
+        assert_eq!(price.get(1), Some(10.0));
+        assert_eq!(price.get(2), Some(-5.0));
+        assert_eq!(price.get(3), None);
+
+        let symbols = df.column("price_currency")?.cast(&DataType::String)?;
+        assert_eq!(symbols.str()?.get(0), Some("$"));
+        assert_eq!(symbols.str()?.get(1), Some("€"));
+        assert!(diagnostics.has(DiagnosticCode::MalformedCurrency));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_split() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,label")?;
+        for id in 0..1000 {
+            writeln!(file, "{},{}", id, if id % 10 < 3 { "pos" } else { "neg" })?;
+        }
+        let loader = CSVLoader::new(file.path(), None)?;
+        let ids = |df: &DataFrame| -> Result<Vec<i64>, Box<dyn Error>> {
+            Ok(df.column("id")?.cast(&DataType::Int64)?.i64()?.into_no_null_iter().collect())
+        };
+
+        let (train, test) = loader.load_split(0.2, 42)?;
+        assert_eq!((train.height(), test.height()), (800, 200));
+        let (train_again, test_again) = loader.load_split(0.2, 42)?;
+        assert_eq!((ids(&train)?, ids(&test)?), (ids(&train_again)?, ids(&test_again)?));
+        assert_ne!(ids(&test)?, ids(&loader.load_split(0.2, 7)?.1)?);
+
+        let mut all = [ids(&train)?, ids(&test)?].concat();
+        all.sort_unstable();
+        assert_eq!(all, (0..1000).collect::<Vec<_>>());
+
+        let (_, test) = loader.load_split_stratified(0.2, 42, "label")?;
+        let positives = test.column("label")?.cast(&DataType::String)?.str()?.into_iter().filter(|l| *l == Some("pos")).count();
+        assert_eq!((test.height(), positives), (200, 60));
+        assert!(loader.load_split(1.5, 42).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_columns() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,created,visits")?;
+        writeln!(file, "1,1700000000,1700000000")?;
+        writeln!(file, "2,0,5")?;
+        writeln!(file, "3,99999999999,7")?;
+        writeln!(file, "4,,9")?;
+
+        let config = LoaderConfig {
+            epoch_columns: vec!["created".to_string()],
+            ..Default::default()
+        };
+        let (df, diagnostics) = CSVLoader::new(file.path(), Some(config))?.load_data_with_diagnostics()?;
+
+        let created = df.column("created")?;
+        assert_eq!(created.dtype(), &DataType::Datetime(TimeUnit::Milliseconds, None));
+        let expected = chrono::NaiveDate::from_ymd_opt(2023, 11, 14)
+            .and_then(|date| date.and_hms_opt(22, 13, 20))
+            .map(|datetime| datetime.and_utc().timestamp_millis());
+        let created = created.datetime()?;
+        assert_eq!(created.get(0), expected);
+        assert_eq!(created.get(1), Some(0));
+        assert_eq!((created.get(2), created.get(3)), (None, None));
+        assert!(diagnostics.has(DiagnosticCode::EpochOutOfRange));
+        assert!(df.column("visits")?.dtype().is_integer());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform_to() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,\"20,7\",B")?;
+
+        let output = NamedTempFile::new()?;
+        let rename = HashMap::from([("category".to_string(), "group".to_string())]);
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let rows = loader.transform_to(output.path(), &["category", "id"], &rename)?;
+
+        assert_eq!(rows, 2);
+        assert_eq!(std::fs::read_to_string(output.path())?, "group,id\nA,1\nB,2\n");
+        assert!(matches!(
+            loader.transform_to(output.path(), &["missing"], &rename),
+            Err(LoaderError::MissingColumn(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_column() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value,category")?;
+        writeln!(file, "1,10.5,A")?;
+        writeln!(file, "2,20.7,")?;
+        writeln!(file, "3,30.2,A")?;
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let values: Vec<f64> = loader.load_column("value")?;
+        assert_eq!(values, vec![10.5, 20.7, 30.2]);
+        let categories: Vec<Option<String>> = loader.load_column("category")?;
+        assert_eq!(categories, vec![Some("A".to_string()), None, Some("A".to_string())]);
+
+        assert!(loader.load_column::<String>("category").is_err());
+        assert!(loader.load_column::<f64>("category").is_err());
+        // 10.5 doesn't survive as an integer, but whole ids do.
+        assert!(loader.load_column::<i64>("value").is_err());
+        assert_eq!(loader.load_column::<i32>("id")?, vec![1, 2, 3]);
+        assert!(matches!(loader.load_column::<f64>("missing"), Err(LoaderError::MissingColumn(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_reader() -> Result<(), Box<dyn Error>> {
+        let csv = "id,value,category\n1,10.5,A\n2,20.7,B\n3,30.2,A\n";
+
+        // A pipe delivers an unsized byte stream, a redirect hands over a file.
+        let piped = CSVLoader::from_reader(Cursor::new(csv.as_bytes()), None)?.load_data()?;
+
+        let mut file = NamedTempFile::new()?;
+        file.write_all(csv.as_bytes())?;
+        let redirected = CSVLoader::from_reader(std::fs::File::open(file.path())?, None)?.load_data()?;
+
+        assert_eq!(piped.shape(), (3, 3));
+        assert!(piped.equals(&redirected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_rows_stops_reading_early() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..1000 {
+            writeln!(file, "{},{}", i, i * 2)?;
+        }
+        // Fails the parse if reading runs past the limit.
+        writeln!(file, "1000,2000,unexpected,fields")?;
+
+        let config = LoaderConfig {
+            max_rows: Some(10),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config.clone()))?;
+
+        // Rows are counted in a streaming pass, so the file is never buffered for the limit.
+        assert!(!loader.reads_into_memory());
+        assert!(matches!(loader.load_data(), Err(LoaderError::RowLimitExceeded(10))));
+        assert!(matches!(loader.scan(), Err(LoaderError::RowLimitExceeded(10))));
+
+        let mut visited = 0;
+        let result = loader.for_each_row(|_| visited += 1);
+        assert!(matches!(result, Err(LoaderError::RowLimitExceeded(10))));
+        assert_eq!(visited, 0);
+
+        let chunked = CSVLoader::new(file.path(), Some(LoaderConfig { chunk_size: Some(4), ..config.clone() }))?;
+        assert!(matches!(chunked.load_data(), Err(LoaderError::RowLimitExceeded(10))));
+        assert!(matches!(chunked.for_each_batch(|_| Ok(())), Err(LoaderError::RowLimitExceeded(10))));
+
+        // Buffered input is counted as it's visited.
+        let bytes = std::fs::read(file.path())?;
+        let mut visited = 0;
+        let result = CSVLoader::from_reader(&bytes[..], Some(config.clone()))?.for_each_row(|_| visited += 1);
+        assert!(matches!(result, Err(LoaderError::RowLimitExceeded(10))));
+        assert_eq!(visited, 10);
+
+        // Within the limit the scan stays lazy, so the bad row only fails the collect.
+        let within = CSVLoader::new(file.path(), Some(LoaderConfig { max_rows: Some(2000), ..config }))?;
+        assert!(within.scan()?.collect().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_bytes() -> Result<(), Box<dyn Error>> {
+        let csv = format!("id,value\n{}", "1,2\n".repeat(1000));
+        let config = LoaderConfig {
+            max_bytes: Some(64),
+            ..Default::default()
+        };
+
+        let mut reader = Cursor::new(csv.as_bytes());
+        let result = CSVLoader::from_reader(&mut reader, Some(config.clone()));
+        assert!(matches!(result, Err(LoaderError::ByteLimitExceeded(64))));
+        assert_eq!(reader.position(), 65);
+
+        let mut file = NamedTempFile::new()?;
+        file.write_all(csv.as_bytes())?;
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+        assert!(matches!(loader.load_data(), Err(LoaderError::ByteLimitExceeded(64))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_category_dictionary_shares_codes() -> Result<(), Box<dyn Error>> {
+        let mut first = NamedTempFile::new()?;
+        writeln!(first, "id,category")?;
+        for (i, category) in ["B", "A", "B", "A", "B", "A"].iter().enumerate() {
+            writeln!(first, "{},{}", i, category)?;
+        }
+        let mut second = NamedTempFile::new()?;
+        writeln!(second, "id,category")?;
+        for (i, category) in ["A", "C", "A", "C", "A", "C", "A", "B"].iter().enumerate() {
+            writeln!(second, "{},{}", i, category)?;
+        }
+
+        let config = LoaderConfig {
+            category_dictionary: Some(CategoryDictionary::new()),
+            ..Default::default()
+        };
+        let first = CSVLoader::new(first.path(), Some(config.clone()))?.load_data()?;
+        let second = CSVLoader::new(second.path(), Some(config))?.load_data()?;
+
+        let code = |df: &DataFrame, row: usize| -> Result<Option<u32>, Box<dyn Error>> {
+            Ok(df.column("category")?.categorical()?.physical().get(row))
+        };
+        // "A" and "B" come first in different orders, but keep one code each.
+        assert_eq!(code(&first, 1)?, code(&second, 0)?);
+        assert_eq!(code(&first, 0)?, code(&second, 7)?);
+        assert_ne!(code(&second, 0)?, code(&second, 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_thread_pool_is_reused_across_loads() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..100 {
+            writeln!(file, "{},{}", i, i)?;
+        }
+
+        let config = LoaderConfig {
+            num_workers: 2,
+            chunk_size: Some(25),
+            ..Default::default()
+        };
+        let loader = CSVLoader::new(file.path(), Some(config))?;
+        assert!(loader.thread_pool.get().is_none());
+
+        loader.load_data()?;
+        let first: *const ThreadPool = loader.thread_pool.get().expect("pool built by the first load");
+        loader.load_data()?;
+
+        assert!(std::ptr::eq(first, loader.thread_pool()?));
+        assert_eq!(loader.thread_pool()?.current_num_threads(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_num_workers_sizes_the_pool() -> Result<(), Box<dyn Error>> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "id,value")?;
+        for i in 0..100 {
+            writeln!(file, "{},{}", i, i * 2)?;
+        }
+
+        for chunk_size in [Some(0), Some(10)] {
+            let config = LoaderConfig {
+                num_workers: 1,
+                chunk_size,
+                ..Default::default()
+            };
+            let loader = CSVLoader::new(file.path(), Some(config))?;
+
+            assert_eq!(loader.load_data()?.shape(), (100, 2));
+            assert_eq!(loader.thread_pool()?.current_num_threads(), 1);
+        }
+
+        let config = LoaderConfig { num_workers: 0, ..Default::default() };
+        let cpus = std::thread::available_parallelism()?.get();
+        assert_eq!(config.worker_count(), cpus);
+        assert_eq!(CSVLoader::new(file.path(), Some(config))?.thread_pool()?.current_num_threads(), cpus);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_member_gzip() -> Result<(), Box<dyn Error>> {
+        use flate2::write::GzEncoder;
+
+        let mut file = tempfile::Builder::new().suffix(".csv.gz").tempfile()?;
+        for part in ["id,value\n1,a\n2,b\n", "3,c\n4,d\n"] {
+            let mut member = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            member.write_all(part.as_bytes())?;
+            file.write_all(&member.finish()?)?;
+        }
+
+        let loader = CSVLoader::new(file.path(), None)?;
+
+        let df = loader.load_data()?;
+        assert_eq!(df.shape(), (4, 2));
+        assert_eq!(loader.load_column::<i64>("id")?, vec![1, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_sink_records_each_load() -> Result<(), Box<dyn Error>> {
+        use crate::metrics::CountingSink;
+
+        let csv = "id,value\n1,a\n2,b\n3,c\n";
+        let sink = Arc::new(CountingSink::default());
+        let config = LoaderConfig {
+            metrics: Some(sink.clone()),
+            max_rows: Some(2),
+            ..Default::default()
+        };
+        let loader = CSVLoader::from_reader(csv.as_bytes(), Some(config.clone()))?;
+        assert!(loader.load_data().is_err());
+        let loader = CSVLoader::from_reader(&csv.as_bytes()[..csv.len() - 4], Some(config))?;
+        loader.load_data()?;
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, "csv");
+        assert_eq!((records[0].1.rows, records[0].1.error.is_some()), (0, true));
+        assert_eq!((records[1].1.rows, records[1].1.bytes, records[1].1.error.is_none()), (2, Some(csv.len() as u64 - 4), true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_inputs() -> Result<(), Box<dyn Error>> {
+        use flate2::write::GzEncoder;
+
+        let csv = "id,value,category\n1,10.5,A\n2,20.7,B\n3,30.2,A\n";
+        let mut plain = NamedTempFile::new()?;
+        plain.write_all(csv.as_bytes())?;
+        let expected = CSVLoader::new(plain.path(), None)?.load_data()?;
+
+        let mut gzip = tempfile::Builder::new().suffix(".csv.gz").tempfile()?;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(csv.as_bytes())?;
+        gzip.write_all(&encoder.finish()?)?;
+
+        // No extension, so the codec comes from the magic bytes.
+        let mut zstd = NamedTempFile::new()?;
+        zstd.write_all(&zstd::encode_all(csv.as_bytes(), 0)?)?;
+
+        for path in [gzip.path(), zstd.path()] {
+            let df = CSVLoader::new(path, None)?.load_data()?;
+            assert_eq!(df.shape(), expected.shape());
+            assert!(df.equals(&expected));
+        }
 
         Ok(())
     }
@@ -0,0 +1,98 @@
+use polars::prelude::PolarsError;
+use thiserror::Error;
+use crate::credentials::CredentialError;
+use crate::csv_loader::DecodeError;
+use crate::s3_loader::S3Error;
+use crate::vector_database::{InvalidTableName, NonFiniteVector};
+use crate::writers::WriterError;
+
+/// The error every loader returns, so callers can match on what went wrong whichever
+/// loader they used. `LoaderError` is an alias for this type.
+#[derive(Error, Debug)]
+pub enum DataVoltError {
+    #[error("Failed to read input: {0}")]
+    IoError(#[source] std::io::Error),
+    #[error("Failed to process data: {0}")]
+    ProcessingError(String),
+    #[error("Polars error: {0}")]
+    Polars(#[from] PolarsError),
+    #[error("Invalid file path: {0}")]
+    InvalidPath(String),
+    #[error("Column not found: {0}")]
+    MissingColumn(String),
+    #[error("Data doesn't match its schema: {}", .0.join("; "))]
+    SchemaViolation(Vec<String>),
+    #[error("Chunk {chunk} has columns {found:?}, expected {expected:?}")]
+    SchemaDrift {
+        chunk: usize,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    #[error("Input has more than {0} rows")]
+    RowLimitExceeded(usize),
+    #[error("Input is larger than {0} bytes")]
+    ByteLimitExceeded(u64),
+    #[error("Memory budget of {budget} bytes is below the estimated result size of {estimated} bytes")]
+    MemoryBudgetExceeded {
+        budget: u64,
+        estimated: u64,
+    },
+    #[error(transparent)]
+    S3(#[from] S3Error),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Query {index} ({query}) failed: {source}")]
+    QueryFailed {
+        index: usize,
+        query: String,
+        source: Box<DataVoltError>,
+    },
+    #[error("{operation} failed: {source}")]
+    OperationFailed {
+        operation: String,
+        source: Box<DataVoltError>,
+    },
+    #[error(transparent)]
+    Credentials(#[from] CredentialError),
+    #[error(transparent)]
+    Writer(#[from] WriterError),
+    #[error(transparent)]
+    InvalidTableName(#[from] InvalidTableName),
+    #[error(transparent)]
+    NonFiniteVector(#[from] NonFiniteVector),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Network request failed: {0}")]
+    Network(String),
+}
+
+impl DataVoltError {
+    /// Wraps `source` with the operation that failed, e.g. "pruning embeddings".
+    pub(crate) fn during(operation: String, source: impl Into<DataVoltError>) -> Self {
+        DataVoltError::OperationFailed { operation, source: Box::new(source.into()) }
+    }
+}
+
+// Transcoding happens inside `Read`, so malformed input arrives as an I/O error. It's a
+// problem with the data rather than the read, so it is reported as one.
+impl From<std::io::Error> for DataVoltError {
+    fn from(e: std::io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<DecodeError>()) {
+            return DataVoltError::ProcessingError(e.to_string());
+        }
+        DataVoltError::IoError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_loader::CSVLoader;
+
+    #[test]
+    fn test_missing_file_is_invalid_path() {
+        let err = CSVLoader::new("does/not/exist.csv", None).err().unwrap();
+
+        assert!(matches!(err, DataVoltError::InvalidPath(ref path) if path.contains("exist.csv")));
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::Display;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use futures::StreamExt;
+use gcp_auth::{CustomServiceAccount, TokenProvider};
+use log::info;
+use polars::prelude::*;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+use crate::error::DataVoltError;
+use crate::metrics::{LoadMetrics, MetricsSink};
+
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com";
+const READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+#[derive(Debug, Deserialize)]
+pub struct Record {
+    pub id: i32,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectFormat {
+    Csv,
+    Parquet,
+    Json,
+    NdJson,
+}
+
+impl ObjectFormat {
+    pub fn from_key(key: &str) -> Option<Self> {
+        let key = key.to_ascii_lowercase();
+        if key.ends_with(".csv") {
+            Some(ObjectFormat::Csv)
+        } else if key.ends_with(".parquet") {
+            Some(ObjectFormat::Parquet)
+        } else if key.ends_with(".ndjson") || key.ends_with(".jsonl") {
+            Some(ObjectFormat::NdJson)
+        } else if key.ends_with(".json") {
+            Some(ObjectFormat::Json)
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(self, bytes: Vec<u8>) -> PolarsResult<DataFrame> {
+        let reader = Cursor::new(bytes);
+        match self {
+            ObjectFormat::Csv => CsvReader::new(reader).finish(),
+            ObjectFormat::Parquet => ParquetReader::new(reader).finish(),
+            ObjectFormat::Json => JsonReader::new(reader).with_json_format(JsonFormat::Json).finish(),
+            ObjectFormat::NdJson => JsonReader::new(reader).with_json_format(JsonFormat::JsonLines).finish(),
+        }
+    }
+}
+
+pub enum GcsAuth {
+    Token(String),
+    ServiceAccount(PathBuf),
+}
+
+pub struct GcsLoader {
+    bucket_name: String,
+    object_name: String,
+    endpoint: String,
+    auth: GcsAuth,
+    client: Client,
+    concurrency_limit: Option<Arc<Semaphore>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+}
+
+impl GcsLoader {
+    pub fn new(bucket_name: &str, object_name: &str, auth: GcsAuth) -> Self {
+        Self::with_endpoint(bucket_name, object_name, auth, DEFAULT_ENDPOINT)
+    }
+
+    pub fn with_endpoint(bucket_name: &str, object_name: &str, auth: GcsAuth, endpoint: &str) -> Self {
+        GcsLoader {
+            bucket_name: bucket_name.to_string(),
+            object_name: object_name.to_string(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            auth,
+            client: Client::new(),
+            concurrency_limit: None,
+            metrics: None,
+        }
+    }
+
+    /// Downloads only while holding a permit from `limit`, which can be shared with other
+    /// loaders to bound how many of them hit the network at once.
+    pub fn with_concurrency_limit(mut self, limit: Arc<Semaphore>) -> Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Receives duration, row and byte counts after every load, recorded as `gcs`.
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    fn record_metrics<T>(&self, started: Instant, bytes: Option<u64>, result: &Result<T, DataVoltError>, rows: impl FnOnce(&T) -> usize) {
+        if let Some(sink) = &self.metrics {
+            sink.record_load("gcs", &LoadMetrics::from_result(started, bytes, result, rows));
+        }
+    }
+
+    async fn access_token(&self) -> Result<String, DataVoltError> {
+        match &self.auth {
+            GcsAuth::Token(token) => Ok(token.clone()),
+            GcsAuth::ServiceAccount(path) => {
+                let account = CustomServiceAccount::from_file(path)
+                    .map_err(|e| DataVoltError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+                let token = account.token(&[READ_ONLY_SCOPE]).await.map_err(network)?;
+                Ok(token.as_str().to_string())
+            },
+        }
+    }
+
+    fn object_url(&self) -> Result<Url, DataVoltError> {
+        let invalid = |reason: String| DataVoltError::InvalidConfig(format!("GCS endpoint {}: {}", self.endpoint, reason));
+        let mut url = Url::parse(&self.endpoint).map_err(|e| invalid(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| invalid("cannot be a base URL".to_string()))?
+            .extend(["storage", "v1", "b", &self.bucket_name, "o", &self.object_name]);
+        url.query_pairs_mut().append_pair("alt", "media");
+        Ok(url)
+    }
+
+    async fn download(&self) -> Result<Vec<u8>, DataVoltError> {
+        let _permit = match &self.concurrency_limit {
+            Some(limit) => Some(limit.acquire().await.map_err(network)?),
+            None => None,
+        };
+        let response = self.client
+            .get(self.object_url()?)
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(network)?;
+
+        let mut stream = response.bytes_stream();
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk.map_err(network)?);
+        }
+
+        info!("Downloaded {} bytes from gs://{}/{}", data.len(), self.bucket_name, self.object_name);
+        Ok(data)
+    }
+
+    pub async fn load_data(&self) -> Result<Vec<Record>, DataVoltError> {
+        let started = Instant::now();
+        let mut bytes = None;
+        let result = async {
+            let data = self.download().await?;
+            bytes = Some(data.len() as u64);
+
+            let mut rdr = csv::Reader::from_reader(&data[..]);
+            let mut records = Vec::new();
+            for result in rdr.deserialize() {
+                let record: Record = result.map_err(|e| DataVoltError::ProcessingError(e.to_string()))?;
+                records.push(record);
+            }
+            Ok(records)
+        }
+        .await;
+        self.record_metrics(started, bytes, &result, Vec::len);
+        result
+    }
+
+    pub async fn load_dataframe(&self) -> Result<DataFrame, DataVoltError> {
+        let started = Instant::now();
+        let mut bytes = None;
+        let result = async {
+            let format = ObjectFormat::from_key(&self.object_name)
+                .ok_or_else(|| DataVoltError::InvalidConfig(format!("Cannot detect format of object {}", self.object_name)))?;
+            let data = self.download().await?;
+            bytes = Some(data.len() as u64);
+            Ok(format.parse(data)?)
+        }
+        .await;
+        self.record_metrics(started, bytes, &result, DataFrame::height);
+        result
+    }
+}
+
+fn network(e: impl Display) -> DataVoltError {
+    DataVoltError::Network(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use crate::metrics::CountingSink;
+    use tokio::net::TcpListener;
+
+    // Serves a single canned response and hands back the raw request it received.
+    async fn mock_gcs(body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        (endpoint, handle)
+    }
+
+    #[tokio::test]
+    async fn test_load_data_from_mock_gcs() -> Result<(), Box<dyn Error>> {
+        let body = "id,value\n1,a\n2,b\n";
+        let (endpoint, server) = mock_gcs(body).await;
+        let sink = Arc::new(CountingSink::default());
+        let loader = GcsLoader::with_endpoint("bucket", "dir/data.csv", GcsAuth::Token("secret".into()), &endpoint)
+            .with_metrics(sink.clone());
+
+        let records = loader.load_data().await?;
+        let request = server.await?;
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].value, "b");
+        assert!(request.starts_with("GET /storage/v1/b/bucket/o/dir%2Fdata.csv?alt=media"));
+        assert!(request.to_ascii_lowercase().contains("authorization: bearer secret"));
+        let recorded = sink.0.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!((recorded[0].0.as_str(), recorded[0].1.rows, recorded[0].1.bytes), ("gcs", 2, Some(body.len() as u64)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_dataframe_detects_ndjson() -> Result<(), Box<dyn Error>> {
+        let (endpoint, server) = mock_gcs("{\"id\":1,\"value\":\"a\"}\n{\"id\":2,\"value\":\"b\"}\n").await;
+        let loader = GcsLoader::with_endpoint("bucket", "events.ndjson", GcsAuth::Token("secret".into()), &endpoint);
+
+        let df = loader.load_dataframe().await?;
+        server.await?;
+
+        assert_eq!(df.shape(), (2, 2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_datavolt_errors() {
+        let unknown = GcsLoader::with_endpoint("bucket", "data.txt", GcsAuth::Token("secret".into()), "http://127.0.0.1:1");
+        let unreachable = GcsLoader::with_endpoint("bucket", "data.csv", GcsAuth::Token("secret".into()), "http://127.0.0.1:1");
+
+        assert!(matches!(unknown.load_dataframe().await, Err(DataVoltError::InvalidConfig(_))));
+        assert!(matches!(unreachable.load_data().await, Err(DataVoltError::Network(_))));
+    }
+}
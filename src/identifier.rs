@@ -0,0 +1,35 @@
+// Every module that builds SQL validates and quotes identifiers through these, so a name
+// accepted in one place is written the same way everywhere else.
+
+/// Whether `name` can appear in SQL unquoted: `[A-Za-z_][A-Za-z0-9_]*`.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Double-quotes `name`, doubling any quotes inside it, so it is used verbatim and keeps
+/// its case.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_identifier() {
+        assert!(is_identifier("created_at"));
+        assert!(is_identifier("_v2"));
+        assert!(!is_identifier("id; DROP TABLE users"));
+        assert!(!is_identifier("1id"));
+        assert!(!is_identifier(""));
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("MixedCase"), r#""MixedCase""#);
+        assert_eq!(quote_identifier(r#"odd "name""#), r#""odd ""name""""#);
+    }
+}
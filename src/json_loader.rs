@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use log::info;
+use polars::io::mmap::MmapBytesReader;
+use polars::prelude::*;
+use crate::csv_loader::{chunk_size_for, LoaderConfig, LoaderError};
+use crate::metrics::{LoadMetrics, MetricsSink};
+use crate::schema::{align_schemas, AlignPolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonLayout {
+    /// An array when the first non-blank character after any byte order mark is `[`, one
+    /// object per line otherwise.
+    #[default]
+    Auto,
+    /// A single JSON array of objects.
+    Array,
+    /// Newline-delimited JSON, one object per line.
+    Lines,
+}
+
+/// The subset of `LoaderConfig` that applies to JSON: how NDJSON is chunked, and where
+/// metrics go. Columns keep the types polars infers, with no filtering or optimization.
+#[derive(Clone)]
+pub struct JsonLoaderConfig {
+    /// Lines per chunk, bypassing the memory-based sizing. `Some(0)` forces a full load.
+    pub chunk_size: Option<usize>,
+    pub memory_budget_bytes: Option<u64>,
+    pub reserved_ram_gb: f64,
+    /// Receives duration, row and byte counts after every `load_data` call.
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+}
+
+impl Default for JsonLoaderConfig {
+    fn default() -> Self {
+        let defaults = LoaderConfig::default();
+        Self {
+            chunk_size: defaults.chunk_size,
+            memory_budget_bytes: defaults.memory_budget_bytes,
+            reserved_ram_gb: defaults.reserved_ram_gb,
+            metrics: None,
+        }
+    }
+}
+
+impl JsonLoaderConfig {
+    // Chunks are sized by the same rules as a CSV's.
+    fn sizing(&self) -> LoaderConfig {
+        LoaderConfig {
+            chunk_size: self.chunk_size,
+            memory_budget_bytes: self.memory_budget_bytes,
+            reserved_ram_gb: self.reserved_ram_gb,
+            ..Default::default()
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Loads a JSON array or NDJSON file into a `DataFrame`. NDJSON is read in chunks of lines
+/// when the configured chunk size, memory budget or free RAM call for it, the way
+/// `CSVLoader` chunks a CSV; an array is always read whole.
+pub struct JsonLoader {
+    file_path: PathBuf,
+    config: JsonLoaderConfig,
+    layout: JsonLayout,
+    flatten: bool,
+    separator: String,
+}
+
+impl JsonLoader {
+    pub fn new<P: AsRef<Path>>(file_path: P, config: Option<JsonLoaderConfig>) -> Result<Self, LoaderError> {
+        let path = file_path.as_ref();
+        if !path.exists() {
+            return Err(LoaderError::InvalidPath(path.display().to_string()));
+        }
+
+        Ok(JsonLoader {
+            file_path: path.to_path_buf(),
+            config: config.unwrap_or_default(),
+            layout: JsonLayout::Auto,
+            flatten: false,
+            separator: ".".to_string(),
+        })
+    }
+
+    pub fn with_layout(mut self, layout: JsonLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Replaces nested objects with one column per leaf field, named by joining the path
+    /// with `separator`, e.g. `user.address.city`. Without it they stay struct columns.
+    pub fn with_flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn load_data(&self) -> Result<DataFrame, LoaderError> {
+        let started = Instant::now();
+        let result = self.read();
+        if let Some(sink) = &self.config.metrics {
+            let bytes = std::fs::metadata(&self.file_path).ok().map(|m| m.len());
+            sink.record_load("json", &LoadMetrics::from_result(started, bytes, &result, DataFrame::height));
+        }
+        result
+    }
+
+    fn read(&self) -> Result<DataFrame, LoaderError> {
+        let Some(first) = self.first_byte()? else {
+            return Ok(DataFrame::default());
+        };
+        let layout = match self.layout {
+            JsonLayout::Auto if first == b'[' => JsonLayout::Array,
+            JsonLayout::Auto => JsonLayout::Lines,
+            layout => layout,
+        };
+
+        let df = match layout {
+            JsonLayout::Array => JsonReader::new(self.open()?)
+                .with_json_format(JsonFormat::Json)
+                .finish()?,
+            _ => self.read_lines()?,
+        };
+        self.prepare(df)
+    }
+
+    // Polars rejects a byte order mark, so a file starting with one is read into memory
+    // without it.
+    fn open(&self) -> Result<Box<dyn MmapBytesReader>, LoaderError> {
+        let mut file = File::open(&self.file_path)?;
+        let mut head = Vec::with_capacity(UTF8_BOM.len());
+        (&mut file).take(UTF8_BOM.len() as u64).read_to_end(&mut head)?;
+        if head != UTF8_BOM {
+            file.seek(SeekFrom::Start(0))?;
+            return Ok(Box::new(file));
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Box::new(Cursor::new(bytes)))
+    }
+
+    fn first_byte(&self) -> Result<Option<u8>, LoaderError> {
+        let mut reader = BufReader::new(File::open(&self.file_path)?);
+        skip_bom(&mut reader)?;
+        loop {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            if let Some(&byte) = buffer.iter().find(|b| !b.is_ascii_whitespace()) {
+                return Ok(Some(byte));
+            }
+            let consumed = buffer.len();
+            reader.consume(consumed);
+        }
+    }
+
+    fn read_lines(&self) -> Result<DataFrame, LoaderError> {
+        let file_size = std::fs::metadata(&self.file_path)?.len();
+        let chunk_size = chunk_size_for(&self.config.sizing(), file_size, || Ok(File::open(&self.file_path)?))?;
+        if chunk_size == 0 {
+            return Ok(JsonLineReader::new(self.open()?).finish()?);
+        }
+
+        // Each chunk infers its own schema, so objects with keys the first chunk lacked
+        // still get their columns.
+        let mut frames = Vec::new();
+        let mut reader = BufReader::new(File::open(&self.file_path)?);
+        skip_bom(&mut reader)?;
+        let mut lines = reader.lines();
+        loop {
+            let mut chunk = Vec::new();
+            let mut rows = 0;
+            for line in lines.by_ref() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                chunk.extend_from_slice(line.as_bytes());
+                chunk.push(b'\n');
+                rows += 1;
+                if rows == chunk_size {
+                    break;
+                }
+            }
+            if rows == 0 {
+                break;
+            }
+            let df = JsonLineReader::new(Cursor::new(chunk)).finish()?;
+            frames.push(self.prepare(df)?);
+        }
+
+        info!("Read {} in {} chunks of up to {} lines", self.file_path.display(), frames.len(), chunk_size);
+        if frames.iter().any(|df| frames[0].schema() != df.schema()) {
+            align_schemas(&mut frames, AlignPolicy::Union)?;
+        }
+        let mut frames = frames.into_iter();
+        let mut df = frames.next().unwrap_or_default();
+        for frame in frames {
+            df.vstack_mut(&frame)?;
+        }
+        df.align_chunks();
+        Ok(df)
+    }
+
+    fn prepare(&self, df: DataFrame) -> Result<DataFrame, LoaderError> {
+        if !self.flatten {
+            return Ok(df);
+        }
+        let mut columns = Vec::with_capacity(df.width());
+        for series in df.get_columns() {
+            flatten_into(series.clone(), &self.separator, &mut columns)?;
+        }
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+fn skip_bom(reader: &mut impl BufRead) -> std::io::Result<()> {
+    if reader.fill_buf()?.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+    Ok(())
+}
+
+// Already-flat frames pass through, so chunks can be flattened before they're combined.
+fn flatten_into(series: Series, separator: &str, columns: &mut Vec<Series>) -> PolarsResult<()> {
+    if !matches!(series.dtype(), DataType::Struct(_)) {
+        columns.push(series);
+        return Ok(());
+    }
+    for field in series.struct_()?.fields() {
+        let mut field = field.clone();
+        field.rename(&format!("{}{}{}", series.name(), separator, field.name()));
+        flatten_into(field, separator, columns)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_loads_array_and_flattens_nested_objects() -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, r#"[
+            {{"id": 1, "user": {{"name": "ada", "address": {{"city": "London"}}}}}},
+            {{"id": 2, "user": {{"name": "alan", "address": {{"city": "Wilmslow"}}}}}}
+        ]"#)?;
+
+        let nested = JsonLoader::new(file.path(), None)?.load_data()?;
+        let flat = JsonLoader::new(file.path(), None)?
+            .with_flatten(true)
+            .with_separator("_")
+            .load_data()?;
+
+        assert!(matches!(nested.column("user")?.dtype(), DataType::Struct(_)));
+        assert_eq!(flat.get_column_names(), ["id", "user_name", "user_address_city"]);
+        assert_eq!(flat.column("user_address_city")?.str()?.get(1), Some("Wilmslow"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_layout_skips_byte_order_mark() -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(UTF8_BOM)?;
+        write!(file, r#"[{{"id": 1}}, {{"id": 2}}]"#)?;
+
+        let array = JsonLoader::new(file.path(), None)?.load_data()?;
+
+        let mut file = NamedTempFile::new()?;
+        file.write_all(UTF8_BOM)?;
+        writeln!(file, r#"{{"id": 1}}"#)?;
+        writeln!(file, r#"{{"id": 2}}"#)?;
+        let lines = JsonLoader::new(file.path(), None)?.load_data()?;
+        let config = JsonLoaderConfig { chunk_size: Some(1), ..Default::default() };
+        let chunked = JsonLoader::new(file.path(), Some(config))?.load_data()?;
+
+        for df in [array, lines, chunked] {
+            assert_eq!(df.column("id")?.i64()?.into_iter().collect::<Vec<_>>(), [Some(1), Some(2)]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_ndjson_matches_full_read() -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = NamedTempFile::new()?;
+        for i in 0..5 {
+            writeln!(file, r#"{{"id": {}, "event": {{"kind": "click"}}}}"#, i)?;
+        }
+        // A key the first chunk never saw.
+        writeln!(file)?;
+        writeln!(file, r#"{{"id": 5, "event": {{"kind": "view"}}, "session": "s1"}}"#)?;
+
+        let load = |chunk_size| -> Result<DataFrame, LoaderError> {
+            let config = JsonLoaderConfig { chunk_size: Some(chunk_size), ..Default::default() };
+            JsonLoader::new(file.path(), Some(config))?.with_flatten(true).load_data()
+        };
+        let full = load(0)?;
+        let chunked = load(2)?;
+
+        assert_eq!(chunked.get_column_names(), ["id", "event.kind", "session"]);
+        assert_eq!(chunked.height(), 6);
+        assert!(chunked.equals_missing(&full.select(chunked.get_column_names())?));
+        assert_eq!(chunked.column("session")?.null_count(), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+pub mod archive_loader;
+pub mod compare;
+pub mod connection;
+pub mod credentials;
+pub mod csv_loader;
+pub mod error;
+pub mod gcs_loader;
+mod identifier;
+pub mod json_loader;
+pub mod metrics;
+pub mod parquet_loader;
+pub mod pipeline;
+pub mod profile;
+pub mod runtime;
+pub mod schema;
+pub mod source;
+pub mod sql_loader;
+pub mod writers;
+
+#[path = "S3_loader.rs"]
+pub mod s3_loader;
+#[path = "Vector_database.rs"]
+pub mod vector_database;
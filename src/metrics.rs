@@ -0,0 +1,74 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+use log::info;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadMetrics {
+    pub duration: Duration,
+    pub rows: usize,
+    /// Size of the input as read from its source, when the loader knows it.
+    pub bytes: Option<u64>,
+    /// The error message if the load failed, in which case `rows` is zero.
+    pub error: Option<String>,
+}
+
+impl LoadMetrics {
+    pub(crate) fn from_result<T, E: Display>(
+        started: Instant,
+        bytes: Option<u64>,
+        result: &Result<T, E>,
+        rows: impl FnOnce(&T) -> usize,
+    ) -> Self {
+        let (rows, error) = match result {
+            Ok(value) => (rows(value), None),
+            Err(e) => (0, Some(e.to_string())),
+        };
+        LoadMetrics {
+            duration: started.elapsed(),
+            rows,
+            bytes,
+            error,
+        }
+    }
+}
+
+/// Receives one `record_load` call per load, successful or not. Implement it to forward
+/// metrics to Prometheus, StatsD or similar.
+pub trait MetricsSink: Send + Sync {
+    fn record_load(&self, name: &str, metrics: &LoadMetrics);
+}
+
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {
+    fn record_load(&self, _name: &str, _metrics: &LoadMetrics) {}
+}
+
+pub struct LoggingMetrics;
+
+impl MetricsSink for LoggingMetrics {
+    fn record_load(&self, name: &str, metrics: &LoadMetrics) {
+        match &metrics.error {
+            Some(error) => info!("{} load failed after {:?}: {}", name, metrics.duration, error),
+            None => info!(
+                "{} load read {} rows ({} bytes) in {:?}",
+                name,
+                metrics.rows,
+                metrics.bytes.map_or_else(|| "unknown".to_string(), |b| b.to_string()),
+                metrics.duration
+            ),
+        }
+    }
+}
+
+/// Keeps every record it receives, so tests can check what a loader reported.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct CountingSink(pub(crate) std::sync::Mutex<Vec<(String, LoadMetrics)>>);
+
+#[cfg(test)]
+impl MetricsSink for CountingSink {
+    fn record_load(&self, name: &str, metrics: &LoadMetrics) {
+        self.0.lock().unwrap().push((name.to_string(), metrics.clone()));
+    }
+}
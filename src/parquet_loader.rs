@@ -0,0 +1,143 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use log::info;
+use polars::prelude::*;
+use polars_parquet::read::{infer_schema, read_metadata, FileReader, RowGroupMetaData};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use crate::csv_loader::{LoaderConfig, LoaderError};
+use crate::metrics::LoadMetrics;
+
+pub struct ParquetLoader {
+    file_path: PathBuf,
+    config: LoaderConfig,
+}
+
+impl ParquetLoader {
+    pub fn new<P: AsRef<Path>>(file_path: P, config: Option<LoaderConfig>) -> Result<Self, LoaderError> {
+        let path = file_path.as_ref();
+        if !path.exists() {
+            return Err(LoaderError::InvalidPath(path.display().to_string()));
+        }
+
+        Ok(ParquetLoader {
+            file_path: path.to_path_buf(),
+            config: config.unwrap_or_default(),
+        })
+    }
+
+    /// Reads every row group on its own rayon worker, at most `num_workers` at a time,
+    /// and stitches the groups back together in file order.
+    pub fn load_data(&self) -> Result<DataFrame, LoaderError> {
+        let started = Instant::now();
+        let result = self.read_row_groups();
+        if let Some(sink) = &self.config.metrics {
+            let bytes = std::fs::metadata(&self.file_path).ok().map(|m| m.len());
+            sink.record_load("parquet", &LoadMetrics::from_result(started, bytes, &result, DataFrame::height));
+        }
+        result
+    }
+
+    fn read_row_groups(&self) -> Result<DataFrame, LoaderError> {
+        let metadata = read_metadata(&mut File::open(&self.file_path)?)
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        let schema = infer_schema(&metadata)
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+        info!(
+            "Reading {} row groups from {} with {} workers",
+            metadata.row_groups.len(),
+            self.file_path.display(),
+            self.config.num_workers
+        );
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.config.num_workers.max(1))
+            .build()
+            .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+
+        let frames: Result<Vec<DataFrame>, LoaderError> = pool.install(|| {
+            metadata.row_groups
+                .par_iter()
+                .map(|row_group| self.read_row_group(row_group, &schema))
+                .collect()
+        });
+
+        let mut frames = frames?.into_iter();
+        let mut df = match frames.next() {
+            Some(df) => df,
+            None => return ParquetReader::new(File::open(&self.file_path)?)
+                .finish()
+                .map_err(|e| LoaderError::ProcessingError(e.to_string())),
+        };
+        for frame in frames {
+            df.vstack_mut(&frame)
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+        }
+        df.as_single_chunk_par();
+
+        Ok(df)
+    }
+
+    fn read_row_group(
+        &self,
+        row_group: &RowGroupMetaData,
+        schema: &ArrowSchema,
+    ) -> Result<DataFrame, LoaderError> {
+        let file = File::open(&self.file_path)?;
+        let reader = FileReader::new(file, vec![row_group.clone()], schema.clone(), None, None, None);
+
+        let mut df = DataFrame::empty();
+        for chunk in reader {
+            let chunk = chunk.map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            let part = DataFrame::try_from((chunk, schema.fields.as_slice()))
+                .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            if df.width() == 0 {
+                df = part;
+            } else {
+                df.vstack_mut(&part)
+                    .map_err(|e| LoaderError::ProcessingError(e.to_string()))?;
+            }
+        }
+
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_parallel_row_groups_match_serial_read() -> Result<(), Box<dyn std::error::Error>> {
+        let ids: Vec<i64> = (0..10_000).collect();
+        let names: Vec<String> = ids.iter().map(|i| format!("row-{}", i)).collect();
+        let scores: Vec<Option<f64>> = ids.iter().map(|i| if i % 7 == 0 { None } else { Some(*i as f64 / 3.0) }).collect();
+        let mut df = df! {
+            "id" => ids,
+            "name" => names,
+            "score" => scores,
+        }?;
+
+        let file = NamedTempFile::new()?;
+        ParquetWriter::new(File::create(file.path())?)
+            .with_row_group_size(Some(1_000))
+            .finish(&mut df)?;
+
+        let row_groups = read_metadata(&mut File::open(file.path())?)?.row_groups.len();
+        assert!(row_groups > 1, "fixture should span several row groups");
+
+        let config = LoaderConfig {
+            num_workers: 4,
+            ..Default::default()
+        };
+        let parallel = ParquetLoader::new(file.path(), Some(config))?.load_data()?;
+        let serial = ParquetReader::new(File::open(file.path())?).finish()?;
+
+        assert!(parallel.equals_missing(&serial));
+
+        Ok(())
+    }
+}
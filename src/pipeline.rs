@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+use log::info;
+use polars::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::csv_loader::{CSVLoader, LoaderConfig, LoaderError};
+use crate::parquet_loader::ParquetLoader;
+use crate::writers::{write_csv, write_ipc, write_parquet, Compression, WritePlan, WriterError};
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error("Failed to read pipeline config: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid pipeline config: {0}")]
+    InvalidConfig(String),
+    #[error(transparent)]
+    Loader(#[from] LoaderError),
+    #[error(transparent)]
+    Writer(#[from] WriterError),
+}
+
+/// Loader settings a CSV source can set from the config file; anything omitted keeps
+/// the `LoaderConfig` default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvSourceOptions {
+    pub num_workers: Option<usize>,
+    pub trim_fields: bool,
+    pub chunk_size: Option<usize>,
+    pub auto_widen: bool,
+    pub preserve_nullable_ints: bool,
+    pub json_columns: Vec<String>,
+    pub currency_columns: Vec<String>,
+    pub max_rows: Option<usize>,
+    pub max_bytes: Option<u64>,
+    pub columns: Option<Vec<String>>,
+}
+
+impl CsvSourceOptions {
+    fn loader_config(&self) -> LoaderConfig {
+        let defaults = LoaderConfig::default();
+        LoaderConfig {
+            num_workers: self.num_workers.unwrap_or(defaults.num_workers),
+            trim_fields: self.trim_fields,
+            chunk_size: self.chunk_size,
+            auto_widen: self.auto_widen,
+            preserve_nullable_ints: self.preserve_nullable_ints,
+            json_columns: self.json_columns.clone(),
+            currency_columns: self.currency_columns.clone(),
+            max_rows: self.max_rows,
+            max_bytes: self.max_bytes,
+            columns: self.columns.clone(),
+            ..defaults
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SourceConfig {
+    Csv {
+        path: PathBuf,
+        #[serde(default)]
+        options: CsvSourceOptions,
+    },
+    Parquet {
+        path: PathBuf,
+        num_workers: Option<usize>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SinkConfig {
+    Parquet {
+        path: PathBuf,
+        #[serde(default)]
+        compression: Compression,
+    },
+    Ipc {
+        path: PathBuf,
+        #[serde(default)]
+        compression: Compression,
+    },
+    Csv {
+        path: PathBuf,
+        #[serde(default)]
+        compression: Compression,
+    },
+}
+
+/// A source-to-sink pipeline described declaratively, e.g. in TOML:
+///
+/// ```toml
+/// [source]
+/// type = "csv"
+/// path = "events.csv"
+///
+/// [sink]
+/// type = "parquet"
+/// path = "events.parquet"
+/// compression = "zstd"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    pub source: SourceConfig,
+    pub sink: SinkConfig,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl PipelineConfig {
+    /// Parses a `.toml`, `.yaml` or `.yml` file, picking the format from the extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, PipelineError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|e| PipelineError::InvalidConfig(e.to_string())),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&contents).map_err(|e| PipelineError::InvalidConfig(e.to_string()))
+            },
+            _ => Err(PipelineError::InvalidConfig(format!(
+                "Unrecognised config format for {}, expected .toml, .yaml or .yml",
+                path.display()
+            ))),
+        }
+    }
+}
+
+fn load_source(source: &SourceConfig) -> Result<DataFrame, PipelineError> {
+    let df = match source {
+        SourceConfig::Csv { path, options } => CSVLoader::new(path, Some(options.loader_config()))?.load_data()?,
+        SourceConfig::Parquet { path, num_workers } => {
+            let defaults = LoaderConfig::default();
+            let config = LoaderConfig {
+                num_workers: num_workers.unwrap_or(defaults.num_workers),
+                ..defaults
+            };
+            ParquetLoader::new(path, Some(config))?.load_data()?
+        },
+    };
+    Ok(df)
+}
+
+/// Loads the configured source and writes it to the configured sink.
+pub fn run_pipeline(config: &PipelineConfig) -> Result<WritePlan, PipelineError> {
+    let df = load_source(&config.source)?;
+    info!("Pipeline source loaded with shape: {:?}", df.shape());
+
+    let plan = match &config.sink {
+        SinkConfig::Parquet { path, compression } => write_parquet(&df, path, *compression, config.dry_run)?,
+        SinkConfig::Ipc { path, compression } => write_ipc(&df, path, *compression, config.dry_run)?,
+        SinkConfig::Csv { path, compression } => write_csv(&df, path, *compression, config.dry_run)?,
+    };
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_csv_to_parquet_pipeline() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let input = dir.path().join("input.csv");
+        let output = dir.path().join("output.parquet");
+        std::fs::write(&input, "id,name\n1, alpha \n2,beta\n")?;
+
+        let config_path = dir.path().join("pipeline.toml");
+        std::fs::write(&config_path, format!(
+            r#"
+            [source]
+            type = "csv"
+            path = "{}"
+            options = {{ trim_fields = true }}
+
+            [sink]
+            type = "parquet"
+            path = "{}"
+            compression = "zstd"
+            "#,
+            input.display(),
+            output.display()
+        ))?;
+
+        let config = PipelineConfig::from_path(&config_path)?;
+        let plan = run_pipeline(&config)?;
+
+        assert_eq!(plan.rows, 2);
+        let written = ParquetReader::new(File::open(&output)?).finish()?;
+        let names = written.column("name")?.cast(&DataType::String)?;
+        assert_eq!(names.str()?.get(0), Some("alpha"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_source_type_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let config_path = dir.path().join("pipeline.yaml");
+        std::fs::write(&config_path, "source:\n  type: ftp\n  path: x\nsink:\n  type: csv\n  path: y\n")?;
+
+        assert!(matches!(PipelineConfig::from_path(&config_path), Err(PipelineError::InvalidConfig(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+use polars::prelude::*;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileOptions {
+    pub histogram_buckets: usize,
+    pub top_k: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self {
+            histogram_buckets: 8,
+            top_k: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+}
+
+/// Buckets widen exponentially away from the column minimum, so the low end of a
+/// skewed distribution keeps its resolution while a long tail still fits.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub null_count: usize,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Profile {
+    pub histograms: BTreeMap<String, Histogram>,
+    pub top_values: BTreeMap<String, Vec<ValueCount>>,
+}
+
+fn histogram(values: &Float64Chunked, buckets: usize) -> Option<Histogram> {
+    let finite: Vec<f64> = values.into_iter().flatten().filter(|v| v.is_finite()).collect();
+    let min = finite.iter().copied().reduce(f64::min)?;
+    let max = finite.iter().copied().reduce(f64::max)?;
+    let buckets = buckets.clamp(1, 62);
+
+    // Bucket i spans [min + (2^i - 1) * unit, min + (2^(i+1) - 1) * unit).
+    let unit = (max - min) / ((1u64 << buckets) - 1) as f64;
+    let mut result: Vec<Bucket> = (0..buckets)
+        .map(|i| Bucket {
+            lower: min + ((1u64 << i) - 1) as f64 * unit,
+            upper: min + ((1u64 << (i + 1)) - 1) as f64 * unit,
+            count: 0,
+        })
+        .collect();
+
+    for value in finite {
+        let idx = if unit > 0.0 {
+            let offset = (value - min) / unit;
+            ((offset + 1.0).log2().floor() as usize).min(buckets - 1)
+        } else {
+            0
+        };
+        result[idx].count += 1;
+    }
+
+    Some(Histogram {
+        min,
+        max,
+        null_count: values.null_count(),
+        buckets: result,
+    })
+}
+
+fn top_values(values: &StringChunked, k: usize) -> Vec<ValueCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values.into_iter().flatten() {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    let mut counts: Vec<ValueCount> = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount { value: value.to_string(), count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts.truncate(k);
+    counts
+}
+
+/// Histograms for numeric columns and the most frequent values of string and
+/// categorical columns.
+pub fn profile_frame(df: &DataFrame, options: &ProfileOptions) -> PolarsResult<Profile> {
+    let mut profile = Profile::default();
+    for series in df.get_columns() {
+        let dtype = series.dtype();
+        if dtype.is_numeric() {
+            let values = series.cast(&DataType::Float64)?;
+            if let Some(h) = histogram(values.f64()?, options.histogram_buckets) {
+                profile.histograms.insert(series.name().to_string(), h);
+            }
+        } else if matches!(dtype, DataType::String | DataType::Categorical(..)) {
+            let values = series.cast(&DataType::String)?;
+            profile.top_values.insert(series.name().to_string(), top_values(values.str()?, options.top_k));
+        }
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_bucket_counts() -> PolarsResult<()> {
+        let df = df!("value" => (0..=15).map(|v| v as f64).collect::<Vec<_>>())?;
+        let options = ProfileOptions { histogram_buckets: 4, top_k: 3 };
+
+        let profile = profile_frame(&df, &options)?;
+
+        let histogram = &profile.histograms["value"];
+        let counts: Vec<usize> = histogram.buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![1, 2, 4, 9]);
+        assert_eq!(histogram.buckets[1].lower, 1.0);
+        assert_eq!(histogram.buckets[3].upper, 15.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_values() -> PolarsResult<()> {
+        let df = df!("category" => ["A", "B", "A", "C", "B", "A"])?;
+        let options = ProfileOptions { histogram_buckets: 4, top_k: 2 };
+
+        let profile = profile_frame(&df, &options)?;
+
+        assert_eq!(profile.top_values["category"], vec![
+            ValueCount { value: "A".to_string(), count: 3 },
+            ValueCount { value: "B".to_string(), count: 2 },
+        ]);
+
+        Ok(())
+    }
+}
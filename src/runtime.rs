@@ -0,0 +1,73 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+use crate::error::DataVoltError;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+// Pools opened by a blocking call keep background tasks on the runtime that opened them,
+// so every blocking call shares one runtime that lives as long as the process. Callers
+// racing to start it may each build one, and the extras are dropped here, which tokio
+// only allows outside async context.
+fn runtime() -> Result<&'static Runtime, DataVoltError> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("datavolt-blocking")
+        .enable_all()
+        .build()?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Runs `future` to completion from synchronous code, on a small runtime shared by every
+/// blocking call in the process.
+///
+/// Starting a runtime inside another one panics in tokio, so callers already inside a
+/// runtime are handled separately: on a multi-threaded runtime the current worker is handed
+/// over with `block_in_place` while the future runs, and on a current-thread runtime, which
+/// would deadlock, an error is returned. Async code should await the loader directly.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output, DataVoltError> {
+    match Handle::try_current() {
+        Err(_) => Ok(runtime()?.block_on(future)),
+        // `runtime()` may drop a runtime, so it runs inside `block_in_place` too.
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| Ok(runtime()?.block_on(future)))
+        },
+        Ok(_) => Err(DataVoltError::ProcessingError(
+            "Blocking load called from a current-thread tokio runtime; await the async method instead".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_on_reuses_one_runtime() -> Result<(), DataVoltError> {
+        // The task waits on the second call, so it is cancelled if the first runtime is dropped.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let task = block_on(async { Handle::current() })?.spawn(rx);
+        let received = block_on(async move {
+            tx.send(42).ok();
+            task.await
+        })?;
+
+        assert_eq!(received.ok().and_then(Result::ok), Some(42));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_inside_multi_thread_runtime() -> Result<(), DataVoltError> {
+        assert_eq!(block_on(async { 1 + 1 })?, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_on_inside_current_thread_runtime_fails() {
+        assert!(matches!(block_on(async {}), Err(DataVoltError::ProcessingError(_))));
+    }
+}
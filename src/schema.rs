@@ -0,0 +1,131 @@
+use polars::prelude::*;
+use polars_core::utils::try_get_supertype;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignPolicy {
+    /// Keep every column seen in any frame, filling the gaps with nulls.
+    Union,
+    /// Keep only the columns present in every frame.
+    Intersection,
+}
+
+pub fn align_schemas(frames: &mut [DataFrame], policy: AlignPolicy) -> PolarsResult<()> {
+    // Columns are ordered by first appearance and widened to the supertype across frames.
+    let mut columns: Vec<(String, DataType)> = Vec::new();
+    for df in frames.iter() {
+        for series in df.get_columns() {
+            match columns.iter_mut().find(|(name, _)| name == series.name()) {
+                Some((_, dtype)) => *dtype = try_get_supertype(dtype, series.dtype())?,
+                None => columns.push((series.name().to_string(), series.dtype().clone())),
+            }
+        }
+    }
+
+    if policy == AlignPolicy::Intersection {
+        columns.retain(|(name, _)| frames.iter().all(|df| df.column(name).is_ok()));
+    }
+
+    for df in frames.iter_mut() {
+        let height = df.height();
+        let aligned = columns
+            .iter()
+            .map(|(name, dtype)| match df.column(name) {
+                Ok(series) => series.cast(dtype),
+                Err(_) => Ok(Series::full_null(name, height, dtype)),
+            })
+            .collect::<PolarsResult<Vec<_>>>()?;
+        *df = DataFrame::new(aligned)?;
+    }
+
+    Ok(())
+}
+
+/// Casts the `keys` columns of every frame to `target` so frames from different loaders
+/// can be joined. Fails if any value would change or be dropped by the cast, e.g. `"abc"`
+/// to Int64 or 1.5 to an integer type.
+pub fn normalize_join_keys(frames: &mut [DataFrame], keys: &[&str], target: DataType) -> PolarsResult<()> {
+    for df in frames.iter_mut() {
+        for key in keys {
+            let original = df.column(key)?;
+            if original.dtype() == &target {
+                continue;
+            }
+
+            let cast = original.strict_cast(&target)?;
+            let round_trip = cast.cast(original.dtype())?;
+            if !round_trip.equals_missing(original) {
+                return Err(PolarsError::ComputeError(
+                    format!("casting join key '{}' from {} to {} loses data", key, original.dtype(), target).into(),
+                ));
+            }
+            df.with_column(cast)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_schemas_union() -> PolarsResult<()> {
+        let mut frames = [
+            df!("id" => [1i32, 2], "price" => [1.5, 2.5])?,
+            df!("label" => ["x"], "id" => [3i64])?,
+        ];
+
+        align_schemas(&mut frames, AlignPolicy::Union)?;
+
+        assert_eq!(frames[0].get_column_names(), ["id", "price", "label"]);
+        assert_eq!(frames[0].schema(), frames[1].schema());
+        assert_eq!(frames[0].column("id")?.dtype(), &DataType::Int64);
+        assert_eq!(frames[1].column("price")?.null_count(), 1);
+
+        let combined = frames[0].vstack(&frames[1])?;
+        assert_eq!(combined.shape(), (3, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_align_schemas_intersection() -> PolarsResult<()> {
+        let mut frames = [
+            df!("id" => [1i32, 2], "price" => [1.5, 2.5])?,
+            df!("label" => ["x"], "id" => [3i64])?,
+        ];
+
+        align_schemas(&mut frames, AlignPolicy::Intersection)?;
+
+        assert_eq!(frames[0].get_column_names(), ["id"]);
+        assert_eq!(frames[1].get_column_names(), ["id"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_join_keys() -> PolarsResult<()> {
+        let mut frames = [
+            df!("id" => [1i32, 2], "price" => [1.5, 2.5])?,
+            df!("id" => [2i64, 3], "label" => ["b", "c"])?,
+        ];
+
+        normalize_join_keys(&mut frames, &["id"], DataType::Int64)?;
+
+        assert_eq!(frames[0].column("id")?.dtype(), &DataType::Int64);
+        let joined = frames[0].inner_join(&frames[1], ["id"], ["id"])?;
+        assert_eq!(joined.height(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_join_keys_rejects_lossy_cast() -> PolarsResult<()> {
+        let mut frames = [df!("id" => [1.0, 2.5])?];
+
+        assert!(normalize_join_keys(&mut frames, &["id"], DataType::Int64).is_err());
+        assert_eq!(frames[0].column("id")?.dtype(), &DataType::Float64);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use async_trait::async_trait;
+use polars::prelude::DataFrame;
+use crate::csv_loader::CSVLoader;
+use crate::error::DataVoltError;
+use crate::s3_loader::S3Loader;
+use crate::sql_loader::SQLLoader;
+
+/// Anything that loads into a `DataFrame`, so pipelines can hold a mix of loaders as
+/// `Box<dyn DataSource>`. The method is async because the network loaders are; local
+/// loaders do their work on the calling task.
+#[async_trait]
+pub trait DataSource: Send + Sync {
+    async fn load(&self) -> Result<DataFrame, DataVoltError>;
+}
+
+#[async_trait]
+impl DataSource for CSVLoader {
+    async fn load(&self) -> Result<DataFrame, DataVoltError> {
+        self.load_data()
+    }
+}
+
+#[async_trait]
+impl DataSource for S3Loader {
+    async fn load(&self) -> Result<DataFrame, DataVoltError> {
+        self.load_dataframe().await
+    }
+}
+
+#[async_trait]
+impl DataSource for SQLLoader {
+    async fn load(&self) -> Result<DataFrame, DataVoltError> {
+        self.load_data().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Duration;
+    use rusoto_core::Region;
+    use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher};
+    use rusoto_s3::S3Client;
+    use tempfile::NamedTempFile;
+    use crate::connection::PgConnection;
+    use crate::sql_loader::{rows_to_frame, FakeRow};
+
+    // Stands in for `SQLLoader` without a server, turning canned rows into a frame the
+    // same way a query result is.
+    struct FakeSqlSource {
+        columns: Vec<(String, String)>,
+        rows: Vec<FakeRow>,
+    }
+
+    #[async_trait]
+    impl DataSource for FakeSqlSource {
+        async fn load(&self) -> Result<DataFrame, DataVoltError> {
+            rows_to_frame(&self.rows, &self.columns, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sources_load_through_trait() -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "id,value\n1,a\n2,b\n")?;
+        let dispatcher = MockRequestDispatcher::default().with_body("id,value\n3,c\n");
+        let client = S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1);
+        let columns = vec![("id".to_string(), "INT8".to_string()), ("value".to_string(), "TEXT".to_string())];
+        let rows = (4..7i64)
+            .map(|id| FakeRow {
+                columns: columns.clone(),
+                cells: vec![Box::new(Some(id)), Box::new(Some(format!("v{}", id)))],
+            })
+            .collect();
+
+        let sources: Vec<Box<dyn DataSource>> = vec![
+            Box::new(CSVLoader::new(file.path(), None)?),
+            Box::new(S3Loader::with_client("bucket", "data.csv", client)),
+            Box::new(FakeSqlSource { columns, rows }),
+        ];
+        let mut heights = Vec::new();
+        for source in &sources {
+            heights.push(source.load().await?.height());
+        }
+
+        assert_eq!(heights, vec![2, 1, 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sql_failure_maps_to_database_error() {
+        let loader = SQLLoader::new("postgres://user@127.0.0.1:1/db", "SELECT 1", 1)
+            .await
+            .with_connection(PgConnection { acquire_timeout: Duration::from_millis(200), ..Default::default() });
+        let source: Box<dyn DataSource> = Box::new(loader);
+
+        assert!(matches!(source.load().await, Err(DataVoltError::Database(_))));
+    }
+}
@@ -0,0 +1,785 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use flate2::write::GzEncoder;
+use log::info;
+use polars::export::arrow::datatypes::{PhysicalType, PrimitiveType};
+use polars::prelude::*;
+use polars_parquet::read::ParquetError;
+use polars_parquet::write::{
+    array_to_columns, to_parquet_schema, transverse, Compressor, DynIter, DynStreamingIterator, Encoding,
+    FallibleStreamingIterator, FileWriter, Version, WriteOptions,
+};
+use serde::Deserialize;
+use thiserror::Error;
+#[cfg(feature = "duckdb")]
+use crate::identifier::quote_identifier;
+
+#[derive(Error, Debug)]
+pub enum WriterError {
+    #[error("Failed to write file: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Failed to write data: {0}")]
+    ProcessingError(String),
+    #[error("Schema does not match existing dataset part {part}: {reason}")]
+    SchemaMismatch { part: String, reason: String },
+    #[error("{compression:?} compression is not supported for {format}")]
+    UnsupportedCompression { format: &'static str, compression: Compression },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    fn unsupported(self, format: &'static str) -> WriterError {
+        WriterError::UnsupportedCompression { format, compression: self }
+    }
+
+    fn parquet(self) -> ParquetCompression {
+        match self {
+            Compression::None => ParquetCompression::Uncompressed,
+            Compression::Snappy => ParquetCompression::Snappy,
+            Compression::Gzip => ParquetCompression::Gzip(None),
+            Compression::Zstd => ParquetCompression::Zstd(None),
+            Compression::Lz4 => ParquetCompression::Lz4Raw,
+        }
+    }
+
+    fn ipc(self) -> Result<Option<IpcCompression>, WriterError> {
+        match self {
+            Compression::None => Ok(None),
+            Compression::Zstd => Ok(Some(IpcCompression::ZSTD)),
+            Compression::Lz4 => Ok(Some(IpcCompression::LZ4)),
+            Compression::Snappy | Compression::Gzip => Err(self.unsupported("IPC")),
+        }
+    }
+}
+
+/// How a Parquet column's values are laid out before they are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnEncoding {
+    Plain,
+    /// Distinct values once, then RLE-packed indices into them. Suits low-cardinality columns.
+    /// Floats, and columns where most values are distinct, are written plain.
+    Dictionary,
+    /// Differences between neighbouring integers, or string lengths. Suits sorted ids and
+    /// timestamps. Columns of other types are written plain.
+    Delta,
+}
+
+/// Overrides for one column in `write_parquet_with_hints`; unset fields keep the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnHint {
+    pub compression: Option<Compression>,
+    pub encoding: Option<ColumnEncoding>,
+}
+
+/// How NaN and infinite floats are written to JSON, which has no literal for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonFiniteFloats {
+    #[default]
+    Null,
+    /// As the strings `"NaN"`, `"Infinity"` and `"-Infinity"`. Still valid JSON, but readers
+    /// have to expect strings in numeric fields.
+    String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JsonOptions {
+    pub non_finite: NonFiniteFloats,
+    /// Rounds floats to this many decimal places.
+    pub float_precision: Option<u32>,
+    /// Writes one object per line (NDJSON) instead of a single array.
+    pub lines: bool,
+}
+
+/// What a write did, or would do when `dry_run` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WritePlan {
+    pub target: PathBuf,
+    pub rows: usize,
+    /// Size of the written file, or the frame's estimated in-memory size for a dry run.
+    pub bytes: u64,
+    pub schema: Schema,
+    pub dry_run: bool,
+}
+
+impl WritePlan {
+    fn new(df: &DataFrame, target: &Path, dry_run: bool) -> Self {
+        WritePlan {
+            target: target.to_path_buf(),
+            rows: df.height(),
+            bytes: df.estimated_size() as u64,
+            schema: df.schema(),
+            dry_run,
+        }
+    }
+
+    // Returns the plan for a dry run, after logging it, so the caller can skip the write.
+    fn dry_run(&self) -> Option<Self> {
+        if !self.dry_run {
+            return None;
+        }
+        info!(
+            "Dry run: would write {} rows (~{} bytes) to {} with schema {:?}",
+            self.rows,
+            self.bytes,
+            self.target.display(),
+            self.schema
+        );
+        Some(self.clone())
+    }
+
+    fn written(mut self) -> Result<Self, WriterError> {
+        self.bytes = std::fs::metadata(&self.target)?.len();
+        info!("Wrote {} rows ({} bytes) to {}", self.rows, self.bytes, self.target.display());
+        Ok(self)
+    }
+}
+
+pub fn write_parquet(df: &DataFrame, path: &Path, compression: Compression, dry_run: bool) -> Result<WritePlan, WriterError> {
+    let plan = WritePlan::new(df, path, dry_run);
+    if let Some(plan) = plan.dry_run() {
+        return Ok(plan);
+    }
+
+    let mut df = df.clone();
+    ParquetWriter::new(File::create(path)?)
+        .with_compression(compression.parquet())
+        .finish(&mut df)
+        .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+
+    plan.written()
+}
+
+// The encodings polars' own writer picks: dictionaries for everything but floats and
+// types without dictionary support.
+fn default_encoding(data_type: &ArrowDataType) -> Encoding {
+    match data_type.to_physical_type() {
+        PhysicalType::Dictionary(_) | PhysicalType::LargeBinary | PhysicalType::LargeUtf8 => Encoding::RleDictionary,
+        PhysicalType::Primitive(PrimitiveType::Float16 | PrimitiveType::Float32 | PrimitiveType::Float64) => Encoding::Plain,
+        PhysicalType::Primitive(_) => Encoding::RleDictionary,
+        _ => Encoding::Plain,
+    }
+}
+
+fn parquet_encoding(data_type: &ArrowDataType, hint: Option<ColumnEncoding>) -> Encoding {
+    let physical = data_type.to_physical_type();
+    match hint {
+        None => default_encoding(data_type),
+        // Categoricals are written as dictionaries whatever is asked for.
+        Some(_) if matches!(physical, PhysicalType::Dictionary(_)) => Encoding::RleDictionary,
+        Some(ColumnEncoding::Plain) => Encoding::Plain,
+        Some(ColumnEncoding::Dictionary) => Encoding::RleDictionary,
+        Some(ColumnEncoding::Delta) => match physical {
+            PhysicalType::Primitive(
+                PrimitiveType::Int8
+                | PrimitiveType::Int16
+                | PrimitiveType::Int32
+                | PrimitiveType::Int64
+                | PrimitiveType::UInt8
+                | PrimitiveType::UInt16
+                | PrimitiveType::UInt32
+                | PrimitiveType::UInt64,
+            ) => Encoding::DeltaBinaryPacked,
+            PhysicalType::Binary | PhysicalType::LargeBinary | PhysicalType::Utf8 | PhysicalType::LargeUtf8 => {
+                Encoding::DeltaLengthByteArray
+            },
+            _ => Encoding::Plain,
+        },
+    }
+}
+
+/// Writes Parquet like `write_parquet`, but with the codec and encoding of the columns
+/// named in `hints` chosen per column. Every other column uses `compression` and the
+/// encoding `write_parquet` would pick.
+pub fn write_parquet_with_hints(
+    df: &DataFrame,
+    path: &Path,
+    compression: Compression,
+    hints: &HashMap<String, ColumnHint>,
+    dry_run: bool,
+) -> Result<WritePlan, WriterError> {
+    if let Some(name) = hints.keys().find(|name| df.column(name).is_err()) {
+        return Err(WriterError::ProcessingError(format!("No column {} to apply a Parquet hint to", name)));
+    }
+    let plan = WritePlan::new(df, path, dry_run);
+    if let Some(plan) = plan.dry_run() {
+        return Ok(plan);
+    }
+
+    let processing = |e: PolarsError| WriterError::ProcessingError(e.to_string());
+    let options = |compression: Compression| WriteOptions {
+        // Readers skip row groups by their min/max, as they can with `write_parquet`'s files.
+        write_statistics: true,
+        compression: compression.parquet().into(),
+        version: Version::V2,
+        data_pagesize_limit: None,
+    };
+
+    let mut df = df.clone();
+    df.align_chunks();
+    let schema = df.schema().to_arrow();
+    let parquet_schema = to_parquet_schema(&schema).map_err(processing)?;
+    let columns: Vec<(WriteOptions, Vec<Encoding>)> = schema
+        .fields
+        .iter()
+        .map(|field| {
+            let hint = hints.get(&field.name).copied().unwrap_or_default();
+            let encodings = transverse(&field.data_type, |data_type| parquet_encoding(data_type, hint.encoding));
+            (options(hint.compression.unwrap_or(compression)), encodings)
+        })
+        .collect();
+
+    let mut writer = FileWriter::try_new(File::create(path)?, schema, options(compression)).map_err(processing)?;
+    for batch in df.iter_chunks().filter(|batch| !batch.is_empty()) {
+        let mut row_group = Vec::new();
+        for ((array, parquet_type), (options, encodings)) in batch.arrays().iter().zip(parquet_schema.fields()).zip(&columns) {
+            for pages in array_to_columns(array, parquet_type.clone(), *options, encodings).map_err(processing)? {
+                let pages = pages.map(|page| page.map_err(|e| ParquetError::FeatureNotSupported(e.to_string())));
+                let compressed = Compressor::new_from_vec(pages, options.compression, vec![])
+                    .map_err(|e| PolarsError::ComputeError(e.to_string().into()));
+                row_group.push(Ok(DynStreamingIterator::new(compressed)));
+            }
+        }
+        writer.write(DynIter::new(row_group.into_iter())).map_err(processing)?;
+    }
+    writer.end(None).map_err(processing)?;
+
+    plan.written()
+}
+
+pub fn write_ipc(df: &DataFrame, path: &Path, compression: Compression, dry_run: bool) -> Result<WritePlan, WriterError> {
+    let ipc_compression = compression.ipc()?;
+    let plan = WritePlan::new(df, path, dry_run);
+    if let Some(plan) = plan.dry_run() {
+        return Ok(plan);
+    }
+
+    let mut df = df.clone();
+    IpcWriter::new(File::create(path)?)
+        .with_compression(ipc_compression)
+        .finish(&mut df)
+        .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+
+    plan.written()
+}
+
+pub fn write_csv(df: &DataFrame, path: &Path, compression: Compression, dry_run: bool) -> Result<WritePlan, WriterError> {
+    if !matches!(compression, Compression::None | Compression::Gzip) {
+        return Err(compression.unsupported("CSV"));
+    }
+    let plan = WritePlan::new(df, path, dry_run);
+    if let Some(plan) = plan.dry_run() {
+        return Ok(plan);
+    }
+
+    let mut df = df.clone();
+    let file = File::create(path)?;
+    if compression == Compression::Gzip {
+        let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+        CsvWriter::new(&mut encoder)
+            .finish(&mut df)
+            .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+        encoder.finish()?.flush()?;
+    } else {
+        CsvWriter::new(file)
+            .finish(&mut df)
+            .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+    }
+
+    plan.written()
+}
+
+fn json_float(value: f64, options: &JsonOptions) -> serde_json::Value {
+    if !value.is_finite() {
+        return match options.non_finite {
+            NonFiniteFloats::Null => serde_json::Value::Null,
+            NonFiniteFloats::String if value.is_nan() => "NaN".into(),
+            NonFiniteFloats::String if value > 0.0 => "Infinity".into(),
+            NonFiniteFloats::String => "-Infinity".into(),
+        };
+    }
+    let value = match options.float_precision {
+        Some(precision) => {
+            let scale = 10f64.powi(precision as i32);
+            (value * scale).round() / scale
+        },
+        None => value,
+    };
+    value.into()
+}
+
+fn json_value(value: &AnyValue, options: &JsonOptions) -> serde_json::Value {
+    match value {
+        AnyValue::Null => serde_json::Value::Null,
+        AnyValue::Boolean(v) => (*v).into(),
+        AnyValue::Int8(v) => (*v).into(),
+        AnyValue::Int16(v) => (*v).into(),
+        AnyValue::Int32(v) => (*v).into(),
+        AnyValue::Int64(v) => (*v).into(),
+        AnyValue::UInt8(v) => (*v).into(),
+        AnyValue::UInt16(v) => (*v).into(),
+        AnyValue::UInt32(v) => (*v).into(),
+        AnyValue::UInt64(v) => (*v).into(),
+        // Widening the f32 directly would print 0.1 as 0.10000000149011612.
+        AnyValue::Float32(v) => json_float(v.to_string().parse().unwrap_or(*v as f64), options),
+        AnyValue::Float64(v) => json_float(*v, options),
+        AnyValue::List(series) => serde_json::Value::Array(
+            (0..series.len())
+                .map(|idx| series.get(idx).map_or(serde_json::Value::Null, |v| json_value(&v, options)))
+                .collect(),
+        ),
+        other => match other.get_str() {
+            Some(s) => s.into(),
+            None => other.to_string().into(),
+        },
+    }
+}
+
+/// Writes `df` as JSON that strict parsers accept: NaN and infinities never appear as bare
+/// literals, whatever `options.non_finite` is set to. Columns keep their frame order.
+pub fn write_json(df: &DataFrame, path: &Path, options: &JsonOptions, dry_run: bool) -> Result<WritePlan, WriterError> {
+    let plan = WritePlan::new(df, path, dry_run);
+    if let Some(plan) = plan.dry_run() {
+        return Ok(plan);
+    }
+
+    let json_err = |e: serde_json::Error| WriterError::ProcessingError(e.to_string());
+    let mut out = BufWriter::new(File::create(path)?);
+    let columns = df.get_columns();
+    if !options.lines {
+        out.write_all(b"[")?;
+    }
+    for idx in 0..df.height() {
+        if idx > 0 {
+            out.write_all(if options.lines { b"\n" } else { b"," })?;
+        }
+        out.write_all(b"{")?;
+        for (position, column) in columns.iter().enumerate() {
+            if position > 0 {
+                out.write_all(b",")?;
+            }
+            let value = column.get(idx).map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+            serde_json::to_writer(&mut out, column.name()).map_err(json_err)?;
+            out.write_all(b":")?;
+            serde_json::to_writer(&mut out, &json_value(&value, options)).map_err(json_err)?;
+        }
+        out.write_all(b"}")?;
+    }
+    out.write_all(if options.lines { b"\n" as &[u8] } else { b"]\n" })?;
+    out.flush()?;
+    drop(out);
+
+    plan.written()
+}
+
+fn parquet_parts(dir: &Path) -> Result<Vec<PathBuf>, WriterError> {
+    let mut parts = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "parquet") {
+            parts.push(path);
+        }
+    }
+    parts.sort();
+    Ok(parts)
+}
+
+fn read_parquet_schema(path: &Path) -> Result<Schema, WriterError> {
+    let df = ParquetReader::new(File::open(path)?)
+        .with_n_rows(Some(0))
+        .finish()
+        .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+    Ok(df.schema())
+}
+
+pub fn append_parquet(df: &DataFrame, dir: &Path, compression: Compression, dry_run: bool) -> Result<WritePlan, WriterError> {
+    if !dry_run {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Every part must share one schema, otherwise the dataset can't be read back as one frame.
+    let existing_parts = if dir.exists() { parquet_parts(dir)? } else { Vec::new() };
+    if let Some(existing) = existing_parts.first() {
+        let expected = read_parquet_schema(existing)?;
+        let actual = df.schema();
+        if expected != actual {
+            return Err(WriterError::SchemaMismatch {
+                part: existing.to_string_lossy().to_string(),
+                reason: format!("expected {:?}, got {:?}", expected, actual),
+            });
+        }
+    }
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| WriterError::ProcessingError(e.to_string()))?
+        .as_nanos();
+    let part_path = dir.join(format!("part-{:020}-{}.parquet", nanos, std::process::id()));
+
+    write_parquet(df, &part_path, compression, dry_run)
+}
+
+pub fn read_parquet_dataset(dir: &Path) -> Result<DataFrame, WriterError> {
+    let mut combined: Option<DataFrame> = None;
+    for part in parquet_parts(dir)? {
+        let df = ParquetReader::new(File::open(&part)?)
+            .finish()
+            .map_err(|e| WriterError::ProcessingError(e.to_string()))?;
+        combined = Some(match combined {
+            Some(acc) => acc.vstack(&df).map_err(|e| WriterError::ProcessingError(e.to_string()))?,
+            None => df,
+        });
+    }
+    combined.ok_or_else(|| WriterError::ProcessingError(format!("No parquet parts in {}", dir.display())))
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_type(data_type: &DataType) -> Option<&'static str> {
+    let sql_type = match data_type {
+        DataType::Boolean => "BOOLEAN",
+        DataType::Int8 => "TINYINT",
+        DataType::Int16 => "SMALLINT",
+        DataType::Int32 => "INTEGER",
+        DataType::Int64 => "BIGINT",
+        DataType::UInt8 => "UTINYINT",
+        DataType::UInt16 => "USMALLINT",
+        DataType::UInt32 => "UINTEGER",
+        DataType::UInt64 => "UBIGINT",
+        DataType::Float32 => "REAL",
+        DataType::Float64 => "DOUBLE",
+        DataType::String | DataType::Categorical(..) => "VARCHAR",
+        DataType::Binary => "BLOB",
+        DataType::Date => "DATE",
+        DataType::Datetime(_, None) => "TIMESTAMP",
+        DataType::Datetime(_, Some(_)) => "TIMESTAMPTZ",
+        DataType::Time => "TIME",
+        _ => return None,
+    };
+    Some(sql_type)
+}
+
+// Each column is read through its typed array once, in order. The appender only binds the
+// scalar types below, so dates and categoricals go in as text and DuckDB casts them to the
+// column type.
+#[cfg(feature = "duckdb")]
+fn duckdb_values(column: &Series) -> Result<Box<dyn Iterator<Item = duckdb::types::Value> + '_>, WriterError> {
+    use duckdb::types::{TimeUnit as DuckTimeUnit, Value};
+
+    fn values<'a, T: 'a>(
+        iter: impl Iterator<Item = Option<T>> + 'a,
+        value: impl Fn(T) -> Value + 'a,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(iter.map(move |v| v.map_or(Value::Null, &value)))
+    }
+
+    let polars_err = |e: PolarsError| WriterError::ProcessingError(e.to_string());
+    Ok(match column.dtype() {
+        DataType::Boolean => values(column.bool().map_err(polars_err)?.into_iter(), Value::Boolean),
+        DataType::Int8 => values(column.i8().map_err(polars_err)?.into_iter(), Value::TinyInt),
+        DataType::Int16 => values(column.i16().map_err(polars_err)?.into_iter(), Value::SmallInt),
+        DataType::Int32 => values(column.i32().map_err(polars_err)?.into_iter(), Value::Int),
+        DataType::Int64 => values(column.i64().map_err(polars_err)?.into_iter(), Value::BigInt),
+        DataType::UInt8 => values(column.u8().map_err(polars_err)?.into_iter(), Value::UTinyInt),
+        DataType::UInt16 => values(column.u16().map_err(polars_err)?.into_iter(), Value::USmallInt),
+        DataType::UInt32 => values(column.u32().map_err(polars_err)?.into_iter(), Value::UInt),
+        DataType::UInt64 => values(column.u64().map_err(polars_err)?.into_iter(), Value::UBigInt),
+        DataType::Float32 => values(column.f32().map_err(polars_err)?.into_iter(), Value::Float),
+        DataType::Float64 => values(column.f64().map_err(polars_err)?.into_iter(), Value::Double),
+        DataType::String => values(column.str().map_err(polars_err)?.into_iter(), |v| Value::Text(v.to_string())),
+        DataType::Binary => values(column.binary().map_err(polars_err)?.into_iter(), |v| Value::Blob(v.to_vec())),
+        DataType::Datetime(unit, _) => {
+            let unit = match unit {
+                TimeUnit::Milliseconds => DuckTimeUnit::Millisecond,
+                TimeUnit::Microseconds => DuckTimeUnit::Microsecond,
+                TimeUnit::Nanoseconds => DuckTimeUnit::Nanosecond,
+            };
+            values(column.datetime().map_err(polars_err)?.into_iter(), move |v| Value::Timestamp(unit, v))
+        },
+        DataType::Time => values(column.time().map_err(polars_err)?.into_iter(), |nanos| {
+            let (secs, nanos) = ((nanos / 1_000_000_000) as u32, (nanos % 1_000_000_000) as u32);
+            let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos).unwrap_or_default();
+            Value::Text(time.format("%H:%M:%S%.6f").to_string())
+        }),
+        _ => {
+            let text = column.cast(&DataType::String).map_err(polars_err)?;
+            let text: Vec<Value> = text.str()
+                .map_err(polars_err)?
+                .into_iter()
+                .map(|v| v.map_or(Value::Null, |v| Value::Text(v.to_string())))
+                .collect();
+            Box::new(text.into_iter())
+        },
+    })
+}
+
+/// Writes `df` into `table` of the DuckDB database at `db_path`, creating the database and
+/// the table as needed. An existing table is appended to, so its columns must line up with
+/// the frame's. The rows go in as one transaction.
+#[cfg(feature = "duckdb")]
+pub fn to_duckdb(df: &DataFrame, db_path: &Path, table: &str) -> Result<WritePlan, WriterError> {
+    let duckdb_err = |e: duckdb::Error| WriterError::ProcessingError(e.to_string());
+    if table.is_empty() {
+        return Err(WriterError::ProcessingError("DuckDB table name is empty".to_string()));
+    }
+
+    let mut columns = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        let sql_type = duckdb_type(column.dtype()).ok_or_else(|| {
+            WriterError::ProcessingError(format!("Column {} has type {} with no DuckDB equivalent", column.name(), column.dtype()))
+        })?;
+        columns.push(format!("{} {}", quote_identifier(column.name()), sql_type));
+    }
+
+    let plan = WritePlan::new(df, db_path, false);
+    let mut conn = duckdb::Connection::open(db_path).map_err(duckdb_err)?;
+    let tx = conn.transaction().map_err(duckdb_err)?;
+    tx.execute_batch(&format!("CREATE TABLE IF NOT EXISTS {} ({})", quote_identifier(table), columns.join(", ")))
+        .map_err(duckdb_err)?;
+    {
+        let mut appender = tx.appender(table).map_err(duckdb_err)?;
+        let mut values = df.get_columns().iter().map(duckdb_values).collect::<Result<Vec<_>, _>>()?;
+        let mut row = Vec::with_capacity(df.width());
+        for _ in 0..df.height() {
+            row.clear();
+            row.extend(values.iter_mut().map(|column| column.next().unwrap_or(duckdb::types::Value::Null)));
+            appender.append_row(duckdb::appender_params_from_iter(&row)).map_err(duckdb_err)?;
+        }
+        appender.flush();
+    }
+    tx.commit().map_err(duckdb_err)?;
+    drop(conn);
+
+    plan.written()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_parquet_twice() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let first = df!("id" => [1i64, 2], "value" => [10.5, 20.7])?;
+        let second = df!("id" => [3i64], "value" => [30.2])?;
+
+        append_parquet(&first, dir.path(), Compression::default(), false)?;
+        append_parquet(&second, dir.path(), Compression::default(), false)?;
+
+        assert_eq!(parquet_parts(dir.path())?.len(), 2);
+        let combined = read_parquet_dataset(dir.path())?;
+        assert_eq!(combined.shape(), (3, 2));
+        assert!(combined.equals(&first.vstack(&second)?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_parquet_rejects_incompatible_schema() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        append_parquet(&df!("id" => [1i64])?, dir.path(), Compression::default(), false)?;
+
+        let result = append_parquet(&df!("id" => ["a"])?, dir.path(), Compression::default(), false);
+
+        assert!(matches!(result, Err(WriterError::SchemaMismatch { .. })));
+        assert_eq!(parquet_parts(dir.path())?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_parquet_zstd_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("frame.parquet");
+        let df = df!("id" => [1i64, 2, 3], "category" => ["A", "B", "A"])?;
+
+        write_parquet(&df, &path, Compression::Zstd, false)?;
+
+        let read_back = ParquetReader::new(File::open(&path)?).finish()?;
+        assert!(read_back.equals(&df));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_csv_gzip() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Read;
+
+        let dir = tempdir()?;
+        let path = dir.path().join("frame.csv.gz");
+        let df = df!("id" => [1i64, 2])?;
+
+        write_csv(&df, &path, Compression::Gzip, false)?;
+
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(File::open(&path)?).read_to_string(&mut contents)?;
+        assert_eq!(contents, "id\n1\n2\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_compression_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let df = df!("id" => [1i64])?;
+
+        let csv = write_csv(&df, &dir.path().join("frame.csv"), Compression::Snappy, false);
+        let ipc = write_ipc(&df, &dir.path().join("frame.arrow"), Compression::Gzip, false);
+
+        assert!(matches!(csv, Err(WriterError::UnsupportedCompression { format: "CSV", .. })));
+        assert!(!dir.path().join("frame.csv").exists());
+        assert!(matches!(ipc, Err(WriterError::UnsupportedCompression { format: "IPC", .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_json_non_finite_floats() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("frame.json");
+        let df = df!(
+            "value" => [1.23456, f64::NAN, f64::INFINITY],
+            "ratio" => [0.1f32, 0.5, 1.0],
+            "label" => ["a", "b", "c"]
+        )?;
+
+        write_json(&df, &path, &JsonOptions::default(), false)?;
+
+        let rows: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(rows[0], serde_json::json!({ "value": 1.23456, "ratio": 0.1, "label": "a" }));
+        assert!(rows[1]["value"].is_null());
+        assert!(rows[2]["value"].is_null());
+
+        let options = JsonOptions {
+            non_finite: NonFiniteFloats::String,
+            float_precision: Some(2),
+            lines: true,
+        };
+        write_json(&df, &path, &options, false)?;
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        let values: Vec<&serde_json::Value> = lines.iter().map(|row| &row["value"]).collect();
+        assert_eq!(values, [&serde_json::json!(1.23), &serde_json::json!("NaN"), &serde_json::json!("Infinity")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_parquet_with_column_hints() -> Result<(), Box<dyn std::error::Error>> {
+        use polars_parquet::parquet::compression::Compression as ParquetCodec;
+        use polars_parquet::read::read_metadata;
+
+        let dir = tempdir()?;
+        let path = dir.path().join("hinted.parquet");
+        let df = df!(
+            "id" => (0..1000i64).collect::<Vec<_>>(),
+            "label" => (0..1000).map(|i| ["red", "green", "blue"][i % 3]).collect::<Vec<_>>(),
+            "score" => (0..1000).map(|i| i as f64 / 7.0).collect::<Vec<_>>(),
+            "bucket" => (0..1000).map(|i| i % 4).collect::<Vec<i64>>(),
+            "raw_bucket" => (0..1000).map(|i| i % 4).collect::<Vec<i64>>()
+        )?;
+        let hints = HashMap::from([
+            ("id".to_string(), ColumnHint { encoding: Some(ColumnEncoding::Delta), compression: Some(Compression::Zstd) }),
+            ("score".to_string(), ColumnHint { encoding: Some(ColumnEncoding::Dictionary), compression: None }),
+            ("bucket".to_string(), ColumnHint { encoding: Some(ColumnEncoding::Dictionary), compression: None }),
+            ("raw_bucket".to_string(), ColumnHint { encoding: Some(ColumnEncoding::Plain), compression: None }),
+        ]);
+
+        write_parquet_with_hints(&df, &path, Compression::Snappy, &hints, false)?;
+
+        let written = ParquetReader::new(File::open(&path)?).finish()?;
+        assert!(written.equals(&df));
+
+        let metadata = read_metadata(&mut File::open(&path)?)?;
+        let columns = metadata.row_groups[0].columns();
+        let encodings = |idx: usize| -> Vec<Encoding> {
+            columns[idx].column_encoding().iter().filter_map(|e| Encoding::try_from(*e).ok()).collect()
+        };
+        assert!(encodings(0).contains(&Encoding::DeltaBinaryPacked));
+        assert!(encodings(1).contains(&Encoding::RleDictionary));
+        assert!(!encodings(2).contains(&Encoding::RleDictionary));
+        assert!(encodings(3).contains(&Encoding::RleDictionary));
+        assert!(!encodings(4).contains(&Encoding::RleDictionary));
+        assert_eq!(columns[0].compression(), ParquetCodec::Zstd);
+        assert_eq!(columns[1].compression(), ParquetCodec::Snappy);
+        assert!(columns.iter().all(|column| column.metadata().statistics.is_some()));
+
+        let unknown = HashMap::from([("missing".to_string(), ColumnHint::default())]);
+        assert!(write_parquet_with_hints(&df, &path, Compression::None, &unknown, false).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[test]
+    fn test_to_duckdb_creates_then_appends() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("frames.duckdb");
+        let df = df!(
+            "id" => [1i64, 2, 3],
+            "name" => [Some("alpha"), None, Some("gamma")],
+            "score" => [0.5f32, 1.5, 2.5],
+            "seen" => [1_700_000_000_000i64, 1_700_000_001_000, 1_700_000_002_000]
+        )?
+        .lazy()
+        .with_column(col("seen").cast(DataType::Datetime(TimeUnit::Milliseconds, None)))
+        .with_column(col("seen").cast(DataType::Date).alias("day"))
+        .collect()?;
+
+        let plan = to_duckdb(&df, &path, "Events")?;
+        to_duckdb(&df, &path, "Events")?;
+
+        assert_eq!(plan.rows, 3);
+        let conn = duckdb::Connection::open(&path)?;
+        let (rows, names, latest, day): (i64, i64, String, String) = conn.query_row(
+            r#"SELECT count(*), count(name), max(seen)::VARCHAR, max(day)::VARCHAR FROM "Events""#,
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        assert_eq!(rows, 6);
+        assert_eq!(names, 4);
+        assert_eq!(latest, "2023-11-14 22:13:22");
+        assert_eq!(day, "2023-11-14");
+
+        let nested = df!("values" => [Series::new("", [1i64])])?;
+        assert!(matches!(to_duckdb(&nested, &path, "nested"), Err(WriterError::ProcessingError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_plans_without_writing() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("frame.csv");
+        let df = df!("id" => [1i64, 2, 3], "value" => ["a", "b", "c"])?;
+
+        let plan = write_csv(&df, &path, Compression::None, true)?;
+
+        assert!(!path.exists());
+        assert!(plan.dry_run);
+        assert_eq!(plan.target, path);
+        assert_eq!(plan.rows, 3);
+        assert!(plan.bytes > 0);
+        assert_eq!(plan.schema, df.schema());
+
+        Ok(())
+    }
+}